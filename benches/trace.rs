@@ -0,0 +1,41 @@
+//! Rendering speed of the default scene.
+//!
+//! Timings depend on the machine, so compare against a baseline measured on the same one:
+//! `cargo bench -- --save-baseline before`, then `cargo bench -- --baseline before`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rt::raytracer::{Camera, CameraBuilder, Scene};
+use std::sync::Arc;
+
+/// Samples per pixel of the benchmark render
+const SAMPLES: u16 = 4;
+
+/// Small camera on the default scene, built anew for every render so none of them starts from
+/// the samples of the one before
+fn camera() -> Camera {
+    CameraBuilder::new()
+        .sample_size(SAMPLES)
+        .default_scene_view()
+        .resolution(64, 48)
+        .build()
+}
+
+/// Render the default scene at a small resolution, measured in camera samples per second
+fn default_scene(c: &mut Criterion) {
+    let scene = Arc::new(Scene::init(0.5));
+
+    let mut group = c.benchmark_group("trace");
+    group.throughput(Throughput::Elements(64 * 48 * SAMPLES as u64));
+    group.sample_size(20);
+    group.bench_function("default_scene", |b| {
+        b.iter_batched(
+            camera,
+            |mut camera| camera.send_rays(scene.clone()),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, default_scene);
+criterion_main!(benches);
@@ -0,0 +1,411 @@
+use rt::integrators::{self, Integrator, INTEGRATORS};
+use rt::raytracer::{Aov, CameraBuilder, Denoiser, ImageFormat, AOVS, IMAGE_FORMATS};
+use rt::samplers::{self, Sampler, SAMPLERS};
+use rt::type_aliases::Point;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The ways to start the program, the GUI only when it is built in
+#[cfg(feature = "gui")]
+const COMMANDS: &str = "\
+Usage: rt                          Open the GUI
+       rt no-gui [options]         Render without the GUI
+       rt worker <address>         Render for coordinators that connect to <address>
+";
+#[cfg(not(feature = "gui"))]
+const COMMANDS: &str = "\
+Usage: rt [options]                Render the scene
+       rt worker <address>         Render for coordinators that connect to <address>
+";
+
+const OPTIONS: &str = "
+Scene and output:
+  --scene <file>                 Scene file to render, the default scene without one
+  --output <file>                Where the image goes, output.ppm by default
+  --format <format>              ppm, png or pfm. Follows the extension of the output by default
+  --aovs <name,...>              Also write these passes next to the output, or all of them:
+                                 depth, normal, albedo, object-id, material-id, direct,
+                                 indirect and emission. Depth and normal need pfm
+
+Render settings:
+  --resolution <width>x<height>  Size of the image, such as 1920x1080
+  --samples <n>                  Samples per pixel
+  --seed <n>                     Seed of the random numbers, random by default
+  --max-depth <n>                Most bounces of a path
+  --threads <n>                  Threads that trace rays, all of them by default
+  --integrator <name>            path, bdpt, photon, ppm, whitted, ao, normals, depth, uv
+                                 or object-id
+  --sampler <name>               sobol, halton, stratified, blue-noise or independent
+  --noise-threshold <x>          Turn on adaptive sampling, with this relative noise as target
+  --denoise                      Smooth the noise of the image, guided by the surfaces it shows
+
+Camera:
+  --position <x,y,z>             Where the camera is
+  --look-at <x,y,z>              Point the camera looks at
+  --focal-length <length>
+  --sensor-width <width>
+  --exposure <stops>             Brighten the image by this many stops, or darken below 0
+
+Long renders:
+  --progressive                  Render in passes and write the image after every pass
+  --time-limit <seconds>         Render in passes and stop when the time is up
+  --checkpoint <file>            Save the render every minute so it can be resumed
+  --resume <file>                Continue from a checkpoint, up to the sample size
+  --workers <address,...>        Render on worker processes, see `rt worker`
+
+  -h, --help                     Show this message
+";
+
+/// Help text of `--help`
+pub fn usage() -> String {
+    format!("{COMMANDS}{OPTIONS}")
+}
+
+/// What the program was asked to do
+pub enum Command {
+    #[cfg(feature = "gui")]
+    Gui,
+    Help,
+    Worker(String),
+    Render(Box<Options>),
+}
+
+/// Settings of a headless render. The ones that are not given come from the scene file, or from
+/// the defaults of the camera.
+#[derive(Default)]
+pub struct Options {
+    pub scene: Option<String>,
+    pub output: Option<String>,
+    pub format: Option<ImageFormat>,
+    pub aovs: Vec<Aov>,
+    pub resolution: Option<(u32, u32)>,
+    pub sample_size: Option<u16>,
+    pub seed: Option<u64>,
+    pub max_depth: Option<u32>,
+    pub threads: Option<usize>,
+    pub integrator: Option<Arc<dyn Integrator>>,
+    pub sampler: Option<Arc<dyn Sampler>>,
+    pub noise_threshold: Option<f64>,
+    pub denoise: bool,
+    pub position: Option<Point>,
+    pub look_at: Option<Point>,
+    pub focal_length: Option<f64>,
+    pub sensor_width: Option<f64>,
+    pub exposure: Option<f64>,
+    pub progressive: bool,
+    pub time_limit: Option<Duration>,
+    pub checkpoint: Option<String>,
+    pub resume: Option<String>,
+    pub workers: Vec<String>,
+}
+
+impl Options {
+    /// Override the settings of `camera` with the ones given on the command line
+    pub fn apply(&self, camera: &mut CameraBuilder) {
+        if let Some((width, height)) = self.resolution {
+            camera.resolution(width, height);
+        }
+        if let Some(sample_size) = self.sample_size {
+            camera.sample_size(sample_size);
+        }
+        if let Some(seed) = self.seed {
+            camera.seed(seed);
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera.max_depth(max_depth);
+        }
+        if let Some(integrator) = &self.integrator {
+            camera.integrator(integrator.clone());
+        }
+        if let Some(sampler) = &self.sampler {
+            camera.sampler(sampler.clone());
+        }
+        if let Some(threshold) = self.noise_threshold {
+            camera.noise_threshold(threshold);
+        }
+        if self.denoise {
+            camera.denoiser(Denoiser::default());
+        }
+        if let Some(position) = self.position {
+            camera.position_by_coordinates(position);
+        }
+        if let Some(look_at) = self.look_at {
+            camera.look_at(look_at);
+        }
+        if let Some(focal_length) = self.focal_length {
+            camera.focal_length(focal_length);
+        }
+        if let Some(sensor_width) = self.sensor_width {
+            camera.sensor_width(sensor_width);
+        }
+        if let Some(exposure) = self.exposure {
+            camera.exposure(exposure);
+        }
+        if let Some(path) = &self.checkpoint {
+            camera.checkpoint(path);
+        }
+        if !self.aovs.is_empty() {
+            camera.aovs(true);
+        }
+    }
+}
+
+/// Read the arguments the program was started with, without the name of the program
+pub fn parse_arguments(args: &[String]) -> Result<Command, String> {
+    if args.is_empty() {
+        #[cfg(feature = "gui")]
+        return Ok(Command::Gui);
+        #[cfg(not(feature = "gui"))]
+        return Ok(Command::Render(Box::default()));
+    }
+
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("{flag} expects a value, see --help"))
+        };
+
+        match flag.as_str() {
+            "no-gui" => {}
+            "-h" | "--help" => return Ok(Command::Help),
+            "worker" => return Ok(Command::Worker(value()?.to_string())),
+            "--scene" => options.scene = Some(value()?.to_string()),
+            "--output" => options.output = Some(value()?.to_string()),
+            "--format" => {
+                let format = value()?;
+                options.format = Some(ImageFormat::from_name(format).ok_or_else(|| {
+                    format!(
+                        "--format expects one of: {}, found `{format}`",
+                        IMAGE_FORMATS.join(", ")
+                    )
+                })?);
+            }
+            "--aovs" => {
+                let names = value()?;
+                options.aovs = match names {
+                    "all" => Aov::ALL.to_vec(),
+                    _ => names
+                        .split(',')
+                        .map(|name| {
+                            Aov::from_name(name.trim()).ok_or_else(|| {
+                                format!(
+                                    "--aovs expects all or some of: {}, found `{name}`",
+                                    AOVS.join(", ")
+                                )
+                            })
+                        })
+                        .collect::<Result<_, _>>()?,
+                };
+            }
+            "--resolution" => {
+                let resolution = value()?;
+                let size = resolution
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                    .filter(|&(width, height)| width > 0 && height > 0);
+                options.resolution = Some(size.ok_or_else(|| {
+                    format!("--resolution expects a size such as 800x600, found `{resolution}`")
+                })?);
+            }
+            "--samples" => {
+                let expected = "a whole number from 1 to 65535";
+                options.sample_size = Some(parse(flag, value()?, expected, |n| *n > 0)?);
+            }
+            "--seed" => options.seed = Some(parse(flag, value()?, "a whole number", |_| true)?),
+            "--max-depth" => {
+                let expected = "a whole number greater than 0";
+                options.max_depth = Some(parse(flag, value()?, expected, |n| *n > 0)?);
+            }
+            "--threads" => {
+                let expected = "a whole number greater than 0";
+                options.threads = Some(parse(flag, value()?, expected, |n| *n > 0)?);
+            }
+            "--integrator" => {
+                let name = value()?;
+                options.integrator = Some(integrators::from_name(name).ok_or_else(|| {
+                    format!(
+                        "--integrator expects one of: {}, found `{name}`",
+                        INTEGRATORS.join(", ")
+                    )
+                })?);
+            }
+            "--sampler" => {
+                let name = value()?;
+                options.sampler = Some(samplers::from_name(name).ok_or_else(|| {
+                    format!(
+                        "--sampler expects one of: {}, found `{name}`",
+                        SAMPLERS.join(", ")
+                    )
+                })?);
+            }
+            "--noise-threshold" => {
+                let expected = "a number greater than 0";
+                options.noise_threshold = Some(parse(flag, value()?, expected, is_positive)?);
+            }
+            "--denoise" => options.denoise = true,
+            "--position" => options.position = Some(vector(flag, value()?)?),
+            "--look-at" => options.look_at = Some(vector(flag, value()?)?),
+            "--focal-length" => {
+                let expected = "a length greater than 0";
+                options.focal_length = Some(parse(flag, value()?, expected, is_positive)?);
+            }
+            "--sensor-width" => {
+                let expected = "a width greater than 0";
+                options.sensor_width = Some(parse(flag, value()?, expected, is_positive)?);
+            }
+            "--exposure" => {
+                let expected = "a number of stops";
+                options.exposure = Some(parse(flag, value()?, expected, |x: &f64| x.is_finite())?);
+            }
+            "--progressive" => options.progressive = true,
+            "--time-limit" => {
+                let seconds = parse(flag, value()?, "a number of seconds", is_positive)?;
+                options.time_limit = Some(Duration::from_secs_f64(seconds));
+            }
+            "--checkpoint" => options.checkpoint = Some(value()?.to_string()),
+            "--resume" => options.resume = Some(value()?.to_string()),
+            "--workers" => {
+                options.workers = value()?.split(',').map(str::to_string).collect();
+            }
+            other => return Err(format!("Unknown option `{other}`, see --help")),
+        }
+    }
+
+    Ok(Command::Render(Box::new(options)))
+}
+
+/// Value of `flag`, when it parses and passes `is_valid`
+fn parse<T: FromStr>(
+    flag: &str,
+    value: &str,
+    expected: &str,
+    is_valid: impl Fn(&T) -> bool,
+) -> Result<T, String> {
+    value
+        .parse()
+        .ok()
+        .filter(is_valid)
+        .ok_or_else(|| format!("{flag} expects {expected}, found `{value}`"))
+}
+
+fn is_positive(number: &f64) -> bool {
+    number.is_finite() && *number > 0.0
+}
+
+/// Point given as `x,y,z`
+fn vector(flag: &str, value: &str) -> Result<Point, String> {
+    let numbers: Vec<f64> = value
+        .split(',')
+        .map(|number| number.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("{flag} expects a point such as 1,2.5,-3, found `{value}`"))?;
+
+    match numbers[..] {
+        [x, y, z] if numbers.iter().all(|number| number.is_finite()) => Ok(Point::new(x, y, z)),
+        _ => Err(format!(
+            "{flag} expects a point such as 1,2.5,-3, found `{value}`"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_line(line: &str) -> Result<Command, String> {
+        let args: Vec<String> = line.split_whitespace().map(str::to_string).collect();
+        parse_arguments(&args)
+    }
+
+    fn options(line: &str) -> Options {
+        match parse_line(line) {
+            Ok(Command::Render(options)) => *options,
+            Ok(_) => panic!("`{line}` is not a render"),
+            Err(error) => panic!("`{line}` failed: {error}"),
+        }
+    }
+
+    fn error(line: &str) -> String {
+        match parse_line(line) {
+            Ok(_) => panic!("`{line}` was accepted"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn options_are_read() {
+        let options = options(
+            "no-gui --scene a.txt --output b.png --resolution 64x48 --samples 9 --seed 3 \
+             --sampler halton --integrator bdpt --position 1,2.5,-3 --aovs depth,albedo --denoise",
+        );
+        assert_eq!(options.scene.as_deref(), Some("a.txt"));
+        assert_eq!(options.output.as_deref(), Some("b.png"));
+        assert_eq!(options.resolution, Some((64, 48)));
+        assert_eq!(options.sample_size, Some(9));
+        assert_eq!(options.seed, Some(3));
+        assert_eq!(options.sampler.unwrap().name(), "halton");
+        assert_eq!(options.integrator.unwrap().name(), "bdpt");
+        assert_eq!(options.position, Some(Point::new(1.0, 2.5, -3.0)));
+        assert_eq!(options.aovs, [Aov::Depth, Aov::Albedo]);
+        assert!(options.denoise);
+    }
+
+    #[test]
+    fn other_commands_are_recognised() {
+        assert!(matches!(parse_line("--help"), Ok(Command::Help)));
+        assert!(matches!(
+            parse_line("worker 0.0.0.0:7878"),
+            Ok(Command::Worker(address)) if address == "0.0.0.0:7878"
+        ));
+        assert_eq!(options("no-gui --aovs all").aovs, Aov::ALL);
+    }
+
+    #[test]
+    fn mistakes_explain_what_was_expected() {
+        assert_eq!(error("--teapot"), "Unknown option `--teapot`, see --help");
+        assert_eq!(
+            error("no-gui --samples"),
+            "--samples expects a value, see --help"
+        );
+        assert_eq!(
+            error("--samples 0"),
+            "--samples expects a whole number from 1 to 65535, found `0`"
+        );
+        assert_eq!(
+            error("--samples 70000"),
+            "--samples expects a whole number from 1 to 65535, found `70000`"
+        );
+        assert_eq!(
+            error("--resolution 800by600"),
+            "--resolution expects a size such as 800x600, found `800by600`"
+        );
+        assert_eq!(
+            error("--position 1,2"),
+            "--position expects a point such as 1,2.5,-3, found `1,2`"
+        );
+        assert_eq!(
+            error("--noise-threshold -1"),
+            "--noise-threshold expects a number greater than 0, found `-1`"
+        );
+        assert_eq!(
+            error("--exposure NaN"),
+            "--exposure expects a number of stops, found `NaN`"
+        );
+        assert_eq!(
+            error("--format gif"),
+            "--format expects one of: ppm, png, pfm, found `gif`"
+        );
+        assert_eq!(
+            error("--sampler random"),
+            format!(
+                "--sampler expects one of: {}, found `random`",
+                SAMPLERS.join(", ")
+            )
+        );
+        assert!(error("--aovs depth,shadow").ends_with("found `shadow`"));
+    }
+}
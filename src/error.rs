@@ -0,0 +1,67 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// ### Error
+///
+/// Everything that can go wrong outside of the render itself: files, scene files, checkpoints,
+/// workers and the GUI.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing `path` failed
+    File { path: PathBuf, source: io::Error },
+    /// Other I/O, such as the connection to a worker
+    Io(io::Error),
+    /// The worker at `index` in the list of a distributed render failed, its tiles went to the
+    /// others
+    Worker { index: usize, source: io::Error },
+    /// Mistake on `line` of a scene file, counted from 1
+    Parse { line: usize, message: String },
+    /// A scene that can not be rendered or saved as it is
+    InvalidScene(String),
+    /// A checkpoint that does not belong to the render
+    Checkpoint(String),
+    /// The GUI could not start
+    Gui(String),
+}
+
+impl Error {
+    /// Wrap an I/O error with the path of the file it happened to
+    pub fn file<P: Into<PathBuf>>(path: P) -> impl FnOnce(io::Error) -> Self {
+        move |source| Error::File {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::File { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Io(source) => write!(f, "{source}"),
+            Error::Worker { index, source } => write!(f, "Worker {index} failed: {source}"),
+            Error::Parse { line, message } => write!(f, "Line {line}: {message}"),
+            Error::InvalidScene(message) => write!(f, "Invalid scene: {message}"),
+            Error::Checkpoint(message) => write!(f, "Invalid checkpoint: {message}"),
+            Error::Gui(message) => write!(f, "Could not start the GUI: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::File { source, .. } | Error::Io(source) | Error::Worker { source, .. } => {
+                Some(source)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
@@ -3,7 +3,7 @@ use gtk::WidgetExt;
 use gtk::{AboutDialog, AboutDialogExt, Button, ButtonExt, GtkWindowExt};
 use gtk::{CssProvider, DialogExt, StyleContextExt};
 
-pub fn about_dialog(scaled_icon: &gdk_pixbuf::Pixbuf) -> AboutDialog {
+pub fn about_dialog(icon: Option<&gdk_pixbuf::Pixbuf>) -> AboutDialog {
     // About Dialog window
     let about_dialog = AboutDialog::new();
     about_dialog.set_program_name("Grit:Lab Ray Tracing Project");
@@ -20,7 +20,7 @@ pub fn about_dialog(scaled_icon: &gdk_pixbuf::Pixbuf) -> AboutDialog {
     ]);
     about_dialog.set_website_label(Some("Code Repository"));
     about_dialog.set_website(Some("https://learn.zone01dakar.sn/git/mouhamadoufadiop/rt"));
-    about_dialog.set_logo(Some(scaled_icon));
+    about_dialog.set_logo(icon);
     about_dialog.set_modal(true);
     about_dialog.set_destroy_with_parent(true);
 
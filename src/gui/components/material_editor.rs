@@ -0,0 +1,84 @@
+use crate::gui::components::buttons::create_button_with_label;
+use crate::gui::components::scales::horizontal_scale;
+use crate::gui::*;
+use crate::textures::Principled;
+
+/// Name, minimum and maximum of every slider in the material editor
+const PARAMETERS: [(&str, f64, f64); 8] = [
+    ("Metallic", 0.0, 1.0),
+    ("Roughness", 0.0, 1.0),
+    ("Specular", 0.0, 1.0),
+    ("Clearcoat", 0.0, 1.0),
+    ("Sheen", 0.0, 1.0),
+    ("Transmission", 0.0, 1.0),
+    ("IOR", 1.0, 3.0),
+    ("Emission", 0.0, 100.0),
+];
+
+/// Button that opens the editor for the `Principled` material of an object.
+///
+/// The emission is stored as a grey color and tinted with the object color in `update.rs`.
+pub fn material_editor_button(material: Rc<RefCell<Principled>>, provider: &CssProvider) -> Button {
+    let button = create_button_with_label("Edit material", provider);
+
+    button.connect_clicked(move |button| {
+        let parent = button
+            .get_toplevel()
+            .and_then(|widget| widget.downcast::<Window>().ok());
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Material editor"),
+            parent.as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[("Close", gtk::ResponseType::Close)],
+        );
+        dialog.set_default_size(400, 0);
+
+        let content = dialog.get_content_area();
+        content.set_border_width(10);
+
+        for (name, min, max) in PARAMETERS {
+            let value = parameter(&material.borrow(), name);
+            let adjustment = gtk::Adjustment::new(value, min, max, 0.01, 0.1, 0.0);
+            let scale = horizontal_scale(name, adjustment, &content);
+            scale.set_digits(2);
+
+            scale.connect_value_changed(clone!(@strong material => move |scale| {
+                set_parameter(&mut material.borrow_mut(), name, scale.get_value());
+            }));
+        }
+
+        dialog.show_all();
+        dialog.run();
+        dialog.close();
+    });
+
+    button
+}
+
+fn parameter(material: &Principled, name: &str) -> f64 {
+    match name {
+        "Metallic" => material.metallic,
+        "Roughness" => material.roughness,
+        "Specular" => material.specular,
+        "Clearcoat" => material.clearcoat,
+        "Sheen" => material.sheen,
+        "Transmission" => material.transmission,
+        "IOR" => material.ior,
+        "Emission" => material.emission.x / 255.0,
+        _ => 0.0,
+    }
+}
+
+fn set_parameter(material: &mut Principled, name: &str, value: f64) {
+    match name {
+        "Metallic" => material.metallic = value,
+        "Roughness" => material.roughness = value,
+        "Specular" => material.specular = value,
+        "Clearcoat" => material.clearcoat = value,
+        "Sheen" => material.sheen = value,
+        "Transmission" => material.transmission = value,
+        "IOR" => material.ior = value,
+        "Emission" => material.emission = Vector3::repeat(value * 255.0),
+        _ => {}
+    }
+}
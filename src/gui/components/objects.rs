@@ -1,8 +1,10 @@
 use crate::gui::components::entries::*;
+use crate::gui::components::material_editor::*;
 use crate::gui::*;
+use crate::textures::Principled;
 use rand::Rng;
 
-const MATERIALS: [&str; 3] = ["Diffusive", "Reflective", "Light"];
+const MATERIALS: [&str; 4] = ["Diffusive", "Reflective", "Light", "Principled"];
 fn append_materials(material_selector: &ComboBoxText) {
     for material in MATERIALS {
         material_selector.append_text(material);
@@ -10,6 +12,27 @@ fn append_materials(material_selector: &ComboBoxText) {
     material_selector.set_active(Some(0));
 }
 
+/// Attach the material editor button, only clickable while `Principled` is selected
+fn add_material_editor(
+    grid: &gtk::Grid,
+    provider: &CssProvider,
+    material_selector: &ComboBoxText,
+    row: i32,
+) -> Rc<RefCell<Principled>> {
+    let principled = Rc::new(RefCell::new(Principled::default()));
+
+    let editor_button = material_editor_button(principled.clone(), provider);
+    editor_button.set_sensitive(false);
+    grid.attach(&editor_button, 0, row, 1, 1);
+
+    material_selector.connect_changed(move |selector| {
+        let selected = selector.get_active_text();
+        editor_button.set_sensitive(selected.as_deref() == Some("Principled"));
+    });
+
+    principled
+}
+
 fn delete_component(flow_box: &FlowBox, id: String) {
     for child in flow_box.get_children().iter() {
         // Attempt to downcast the child to GtkFlowBoxChild
@@ -26,10 +49,7 @@ fn delete_component(flow_box: &FlowBox, id: String) {
 }
 
 pub fn create_sphere_section(app_state: Rc<RefCell<AppState>>, flow_box: FlowBox) -> gtk::Widget {
-    let provider = CssProvider::new();
-    provider
-        .load_from_path("src/gui/style.css")
-        .expect("Failed to load CSS");
+    let provider = style_provider();
 
     let sphere_count = app_state.borrow().spheres.len() + 1;
     let unique_id = format!("sphere_{}", sphere_count); // Generate unique ID
@@ -70,6 +90,8 @@ pub fn create_sphere_section(app_state: Rc<RefCell<AppState>>, flow_box: FlowBox
     let color_button = gtk::ColorButton::new();
     grid.attach(&color_button, 0, 12, 1, 1); // Column 0, Row 12
 
+    let principled = add_material_editor(&grid, &provider, &material_selector, 13);
+
     let sphere_config = SphereConfig {
         id: Rc::new(RefCell::new(sphere_count as u32)),
         pos_x_entry: Rc::new(RefCell::new(pos_x_entry.clone())),
@@ -78,11 +100,12 @@ pub fn create_sphere_section(app_state: Rc<RefCell<AppState>>, flow_box: FlowBox
         radius_entry: Rc::new(RefCell::new(radius_entry.clone())),
         material_selector: Rc::new(RefCell::new(material_selector.clone())),
         color_button: Rc::new(RefCell::new(color_button.clone())),
+        principled,
     };
 
     // Create a randomize button for the sphere section
     let randomize_button = gtk::Button::with_label("Randomize");
-    grid.attach(&randomize_button, 0, 13, 1, 1); // Adjust the row number as needed
+    grid.attach(&randomize_button, 0, 14, 1, 1); // Adjust the row number as needed
 
     // Connect the randomize button click handler
     let pos_x_entry_clone = pos_x_entry.clone();
@@ -107,7 +130,7 @@ pub fn create_sphere_section(app_state: Rc<RefCell<AppState>>, flow_box: FlowBox
     let delete_id = sphere_config.id.clone();
     let delete_button = Button::with_label("Delete");
     println!("Adding delete button with ID: {}", *delete_id.borrow());
-    grid.attach(&delete_button, 0, 14, 1, 1); // Column 0, Row 13
+    grid.attach(&delete_button, 0, 15, 1, 1); // Column 0, Row 15
 
     // Connect the delete button click handler
     delete_button.connect_clicked(clone!(@strong app_state, @strong flow_box => move |_| {
@@ -135,10 +158,7 @@ pub fn create_cylinder_section(
     app_state: Rc<RefCell<AppState>>,
     flow_box: gtk::FlowBox,
 ) -> gtk::Widget {
-    let provider = CssProvider::new();
-    provider
-        .load_from_path("src/gui/style.css")
-        .expect("Failed to load CSS");
+    let provider = style_provider();
 
     let cylinder_count = app_state.borrow().cylinders.len() + 1;
     let unique_id = format!("cylinder_{}", cylinder_count); // Generate unique ID
@@ -178,6 +198,8 @@ pub fn create_cylinder_section(
     let color_button = gtk::ColorButton::new();
     grid.attach(&color_button, 0, 14, 1, 1);
 
+    let principled = add_material_editor(&grid, &provider, &material_selector, 15);
+
     let cylinder_config = CylinderConfig {
         id: Rc::new(RefCell::new(cylinder_count as u32)),
         pos_x_entry: Rc::new(RefCell::new(pos_x_entry.clone())),
@@ -187,10 +209,11 @@ pub fn create_cylinder_section(
         height_entry: Rc::new(RefCell::new(height_entry.clone())),
         material_selector: Rc::new(RefCell::new(material_selector.clone())),
         color_button: Rc::new(RefCell::new(color_button.clone())),
+        principled,
     };
 
     let randomize_button = gtk::Button::with_label("Randomize");
-    grid.attach(&randomize_button, 0, 15, 1, 1); // Adjust the row number as needed
+    grid.attach(&randomize_button, 0, 16, 1, 1); // Adjust the row number as needed
 
     // Connect the randomize button click handler
     let pos_x_entry_clone = pos_x_entry.clone();
@@ -216,7 +239,7 @@ pub fn create_cylinder_section(
     // Create a delete button for the cylinder section
     let delete_id = cylinder_config.id.clone();
     let delete_button = gtk::Button::with_label("Delete");
-    grid.attach(&delete_button, 0, 16, 1, 1); // Column 0, Row 15
+    grid.attach(&delete_button, 0, 17, 1, 1); // Column 0, Row 17
 
     // Connect the delete button click handler
     delete_button.connect_clicked(clone!(@strong app_state, @strong flow_box => move |_| {
@@ -246,10 +269,7 @@ pub fn create_cube_section(
     app_state: Rc<RefCell<AppState>>,
     flow_box: gtk::FlowBox,
 ) -> gtk::Widget {
-    let provider = CssProvider::new();
-    provider
-        .load_from_path("src/gui/style.css")
-        .expect("Failed to load CSS");
+    let provider = style_provider();
 
     let cube_count = app_state.borrow().cubes.len() + 1;
     let unique_id = format!("cube_{}", cube_count); // Generate unique ID
@@ -288,6 +308,8 @@ pub fn create_cube_section(
     let color_button = gtk::ColorButton::new();
     grid.attach(&color_button, 0, 12, 1, 1); // Column 0, Row 12
 
+    let principled = add_material_editor(&grid, &provider, &material_selector, 13);
+
     grid.set_widget_name(&unique_id);
 
     let cube_config = CubeConfig {
@@ -298,10 +320,11 @@ pub fn create_cube_section(
         radius_entry: Rc::new(RefCell::new(radius_entry.clone())),
         material_selector: Rc::new(RefCell::new(material_selector.clone())),
         color_button: Rc::new(RefCell::new(color_button.clone())),
+        principled,
     };
 
     let randomize_button = gtk::Button::with_label("Randomize");
-    grid.attach(&randomize_button, 0, 13, 1, 1); // Adjust the row number as needed
+    grid.attach(&randomize_button, 0, 14, 1, 1); // Adjust the row number as needed
 
     // Connect the randomize button click handler
     let pos_x_entry_clone = pos_x_entry.clone();
@@ -323,7 +346,7 @@ pub fn create_cube_section(
     });
     let delete_id = cube_config.id.clone();
     let delete_button = gtk::Button::with_label("Delete");
-    grid.attach(&delete_button, 0, 14, 1, 1); //Column 0, Row 13
+    grid.attach(&delete_button, 0, 15, 1, 1); //Column 0, Row 15
 
     // Connect a handler to the delete button
     delete_button.connect_clicked(clone!(@strong app_state, @strong flow_box => move |_| {
@@ -351,10 +374,7 @@ pub fn create_flat_plane_section(
     app_state: Rc<RefCell<AppState>>,
     flow_box: FlowBox,
 ) -> gtk::Widget {
-    let provider = CssProvider::new();
-    provider
-        .load_from_path("src/gui/style.css")
-        .expect("Failed to load CSS");
+    let provider = style_provider();
 
     let flat_plane_count = app_state.borrow().flat_planes.len() + 1;
     let unique_id = format!("flat_plane_{}", flat_plane_count); // Generate unique ID
@@ -393,6 +413,8 @@ pub fn create_flat_plane_section(
     let color_button = gtk::ColorButton::new();
     grid.attach(&color_button, 0, 12, 1, 1);
 
+    let principled = add_material_editor(&grid, &provider, &material_selector, 13);
+
     grid.set_widget_name(&unique_id);
 
     let flat_plane_config = FlatPlaneConfig {
@@ -403,11 +425,12 @@ pub fn create_flat_plane_section(
         radius_entry: Rc::new(RefCell::new(radius_entry.clone())),
         material_selector: Rc::new(RefCell::new(material_selector)),
         color_button: Rc::new(RefCell::new(color_button)),
+        principled,
     };
 
     let delete_id = flat_plane_config.id.clone();
     let delete_button = gtk::Button::with_label("Delete");
-    grid.attach(&delete_button, 0, 13, 1, 1); //Column 0, Row 13
+    grid.attach(&delete_button, 0, 14, 1, 1); //Column 0, Row 14
 
     // Connect a handler to the delete button
     delete_button.connect_clicked(clone!(@strong app_state, @strong flow_box => move |_| {
@@ -1,9 +1,20 @@
 use crate::gui::components::*;
 use crate::gui::GtkBox as Box;
 use crate::gui::*;
-use crate::raytracer::CameraBuilder;
+use crate::integrators::{from_name, PathTracer, INTEGRATORS};
+use crate::raytracer::{CameraBuilder, Denoiser, Issue, Progress, RenderStats, Subject};
+use crate::samplers::{self, Sobol, SAMPLERS};
+use crate::Error;
+
+/// What the render thread tells the interface
+enum RenderUpdate {
+    Progress(Progress),
+    Done(RenderStats),
+    Failed(Error),
+}
 
-pub fn launch_gui() {
+/// Open the settings window and run the interface until it is closed
+pub fn launch_gui() -> Result<(), Error> {
     let app_state = Rc::new(RefCell::new(AppState {
         spheres: Vec::new(),
         cylinders: Vec::new(),
@@ -12,13 +23,10 @@ pub fn launch_gui() {
         brightness: 0.5,
     }));
 
-    gtk::init().expect("Failed to initialize GTK.");
+    gtk::init().map_err(|error| Error::Gui(error.to_string()))?;
 
     // Create a CSS provider
-    let provider = CssProvider::new();
-    provider
-        .load_from_path("src/gui/style.css")
-        .expect("Failed to load CSS");
+    let provider = style_provider();
 
     let window = Window::new(WindowType::Toplevel);
     window.set_resizable(false); // Allows the window to be resized
@@ -29,17 +37,10 @@ pub fn launch_gui() {
         .get_style_context()
         .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
 
-    let icon = Pixbuf::from_file("src/gui/RT.png").expect("Failed to load icon");
-    let scaled_icon = icon
-        .scale_simple(64, 64, gdk_pixbuf::InterpType::Bilinear)
-        .expect("Failed to scale icon");
-    window.set_icon(Some(&scaled_icon));
+    let icon = app_icon(64);
+    window.set_icon(icon.as_ref());
 
     let scrolled_window = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
-    match Pixbuf::from_file("src/gui/RT.png") {
-        Ok(icon) => window.set_icon(Some(&icon)),
-        Err(err) => std::eprintln!("Failed to load icon: {}", err),
-    }
 
     let vertical_box = GtkBox::new(Orientation::Vertical, 0);
     let top_horizontal_box = GtkBox::new(Orientation::Horizontal, 10);
@@ -47,7 +48,7 @@ pub fn launch_gui() {
     vertical_box.set_spacing(10);
 
     // About window and button
-    let about_dialog = about_dialog(&scaled_icon);
+    let about_dialog = about_dialog(icon.as_ref());
     about_dialog.set_transient_for(Some(&window));
     let about_button = about_btn(&about_dialog, &provider);
 
@@ -62,11 +63,45 @@ pub fn launch_gui() {
     let sample_size_scale = horizontal_scale("Sample size", adjustment, &vertical_box);
     sample_size_scale.set_digits(0);
 
+    // Max depth
+    let adjustment = gtk::Adjustment::new(50.0, 1.0, 200.0, 1.0, 10.0, 0.0);
+    let max_depth_scale = horizontal_scale("Max depth", adjustment, &vertical_box);
+    max_depth_scale.set_digits(0);
+
+    // Noise threshold, 0 turns adaptive sampling off
+    let adjustment = gtk::Adjustment::new(0.0, 0.0, 0.2, 0.005, 0.01, 0.0);
+    let noise_threshold_scale = horizontal_scale("Noise threshold", adjustment, &vertical_box);
+    noise_threshold_scale.set_digits(3);
+
+    // Integrator
+    let integrator_label = gtk::Label::new(Some("Integrator"));
+    vertical_box.pack_start(&integrator_label, false, false, 0);
+    let integrator_selector = ComboBoxText::new();
+    for name in INTEGRATORS {
+        integrator_selector.append_text(name);
+    }
+    integrator_selector.set_active(Some(0));
+    vertical_box.pack_start(&integrator_selector, false, false, 0);
+
+    // Sampler
+    let sampler_label = gtk::Label::new(Some("Sampler"));
+    vertical_box.pack_start(&sampler_label, false, false, 0);
+    let sampler_selector = ComboBoxText::new();
+    for name in SAMPLERS {
+        sampler_selector.append_text(name);
+    }
+    sampler_selector.set_active(Some(0));
+    vertical_box.pack_start(&sampler_selector, false, false, 0);
+
+    // Denoiser
+    let denoise_button = gtk::CheckButton::with_label("Denoise");
+    vertical_box.pack_start(&denoise_button, false, false, 0);
+
     let dual_scales = gtk::Box::new(Orientation::Horizontal, 0);
 
     // Brightness
     let brightness_box = gtk::Box::new(Orientation::Vertical, 0);
-    let adjustment = gtk::Adjustment::new(0.5, 0.0, 1.0, 0.01, 0.01, 0.0);
+    let adjustment = gtk::Adjustment::new(0.5, 0.0, 5.0, 0.01, 0.01, 0.0);
     let brightness_scale = horizontal_scale("Brightness", adjustment, &brightness_box);
     brightness_scale.set_value(0.5);
     brightness_scale.set_digits(2);
@@ -78,8 +113,16 @@ pub fn launch_gui() {
     focal_length_scale.set_value(1.0);
     focal_length_scale.set_digits(2);
 
+    // Exposure
+    let exposure_box = gtk::Box::new(Orientation::Vertical, 0);
+    let adjustment = gtk::Adjustment::new(0.0, -5.0, 5.0, 0.1, 0.1, 0.0);
+    let exposure_scale = horizontal_scale("Exposure", adjustment, &exposure_box);
+    exposure_scale.set_value(0.0);
+    exposure_scale.set_digits(1);
+
     dual_scales.pack_start(&brightness_box, true, true, 0);
     dual_scales.pack_start(&focal_length_box, true, true, 0);
+    dual_scales.pack_start(&exposure_box, true, true, 0);
     vertical_box.pack_start(&dual_scales, false, true, 0);
 
     let app_state_clone = app_state.clone();
@@ -149,18 +192,19 @@ pub fn launch_gui() {
     vertical_box.pack_start(&render_button, false, false, 0);
 
     // Define CSS styles for the message label
-    let red_style =
-        "<span foreground='red'>Invalid input detected. Please enter numbers in 0.0 format.</span>";
     let green_style =
         "<span foreground='green'>All inputs are valid. Proceeding with rendering.</span>";
 
-    let render_done_style = "<span foreground='green'>Rendering done.</span>";
-
     // Create a label for displaying messages
     let message_label = gtk::Label::new(None);
     message_label.set_text("Ready"); // Default text
     vertical_box.pack_start(&message_label, false, false, 10); // Adjust packing as needed
 
+    let progress_bar = ProgressBar::new();
+    progress_bar.set_show_text(true);
+    progress_bar.set_text(Some(""));
+    vertical_box.pack_start(&progress_bar, false, false, 0);
+
     let show_image_button = Button::with_label("Show Image");
     vertical_box.pack_start(&show_image_button, false, false, 0);
 
@@ -176,29 +220,21 @@ pub fn launch_gui() {
     });
 
     // Render Button
-    render_button.connect_clicked(clone!(@strong app_state, @strong message_label => move |_| {
+    render_button.connect_clicked(clone!(@strong app_state, @strong message_label, @strong progress_bar => move |button| {
         let app_state_borrowed = app_state.borrow();
-        let mut all_inputs_valid = true;
-
-        if !validate_spheres(&app_state_borrowed.spheres) {
-            message_label.set_markup("<span foreground='red'>Invalid sphere detected.</span>");
-            return;
-        }
-
-        if !validate_cylinders(&app_state_borrowed.cylinders) {
-            message_label.set_markup("<span foreground='red'>Invalid cylinder detected.</span>");
-            return;
-        }
-
-        if !validate_cubes(&app_state_borrowed.cubes) {
-            message_label.set_markup("<span foreground='red'>Invalid cube detected.</span>");
-            return;
-        }
-
-        if !validate_flat_planes(&app_state_borrowed.flat_planes) {
-            message_label.set_markup("<span foreground='red'>Invalid flat plane detected.</span>");
-            return;
-        }
+        let mut issues = entry_issues(&app_state_borrowed);
+
+        // Camera entries, with the field of the scene each of them sets
+        let camera_entries = [
+            ("position", &cam_x_entry),
+            ("position", &cam_y_entry),
+            ("position", &cam_z_entry),
+            ("look_at", &look_at_x_entry),
+            ("look_at", &look_at_y_entry),
+            ("look_at", &look_at_z_entry),
+            ("resolution", &width_entry),
+            ("resolution", &height_entry),
+        ];
 
         let mut cam_x = 0.0;
         let mut cam_y = 0.0;
@@ -211,7 +247,19 @@ pub fn launch_gui() {
 
 
         let sample_size = sample_size_scale.get_value() as u16;
+        let max_depth = max_depth_scale.get_value() as u32;
+        let noise_threshold = noise_threshold_scale.get_value();
+        let integrator = integrator_selector
+            .get_active_text()
+            .and_then(|name| from_name(&name))
+            .unwrap_or_else(|| Arc::new(PathTracer));
+        let sampler = sampler_selector
+            .get_active_text()
+            .and_then(|name| samplers::from_name(&name))
+            .unwrap_or_else(|| Arc::new(Sobol));
         let focal_length = focal_length_scale.get_value();
+        let exposure = exposure_scale.get_value();
+        let denoise = denoise_button.get_active();
         if let (Ok(x), Ok(y), Ok(z), Ok(look_x), Ok(look_y), Ok(look_z), Ok(w), Ok(h)) = (
             cam_x_entry.get_text().parse::<f64>(),
             cam_y_entry.get_text().parse::<f64>(),
@@ -231,38 +279,136 @@ pub fn launch_gui() {
             width = w;
             height = h;
         } else {
-            all_inputs_valid = false;
+            for (field, entry) in camera_entries {
+                let text = entry.get_text();
+                let is_number = match field {
+                    "resolution" => text.parse::<u32>().is_ok(),
+                    _ => text.parse::<f64>().is_ok(),
+                };
+                if !is_number {
+                    issues.push(Issue { subject: Subject::Camera, field, reason: "must be a number" });
+                }
+            }
         }
-        let message_label_clone = message_label.clone();
-
-        if all_inputs_valid {
-            println!("All inputs are valid. Proceeding with rendering.");
-            message_label.set_markup(green_style);
 
-            // Schedule rendering to start after a short delay
-            glib::timeout_add_local(50, clone!(@strong app_state => move || {
-                const OUTPUT_PATH: &str = "output.ppm";
-                let updated_scene = Arc::new(update_scene_from_gui(app_state.clone()));
+        let mut builder = CameraBuilder::new();
+        if noise_threshold > 0.0 {
+            builder.noise_threshold(noise_threshold);
+        }
+        if denoise {
+            builder.denoiser(Denoiser::default());
+        }
+        builder
+            .sample_size(sample_size)
+            .max_depth(max_depth)
+            .integrator(integrator)
+            .sampler(sampler)
+            .position_by_coordinates(Vector3::new(cam_x, cam_y, cam_z))
+            .look_at(Vector3::new(look_at_x, look_at_y, look_at_z))
+            .focal_length(focal_length)
+            .resolution(width, height)
+            .sensor_width(1.0)
+            .exposure(exposure);
+
+        // The scene is only checked once every entry is a number, and the entries it is wrong
+        // about are marked
+        let scene = Arc::new(update_scene_from_gui(app_state.clone()));
+        if issues.is_empty() {
+            issues = scene.validate(&builder.clone().build());
+        }
+        mark_issues(&app_state_borrowed, &issues);
+        message_label.set_tooltip_text(None);
+        for (field, entry) in camera_entries {
+            let issue = issues
+                .iter()
+                .find(|issue| issue.subject == Subject::Camera && issue.field == field);
+            mark_entry(entry, issue);
+        }
+        if let Some(issue) = issues.first() {
+            let message = issue_message(&app_state_borrowed, issue);
+            message_label.set_markup(&format!(
+                "<span foreground='red'>{}</span>",
+                glib::markup_escape_text(&message)
+            ));
+            return;
+        }
 
-                let mut camera = CameraBuilder::new()
-                .sample_size(sample_size)
-                .position_by_coordinates(Vector3::new(cam_x, cam_y, cam_z))
-                .look_at(Vector3::new(look_at_x, look_at_y, look_at_z))
-                .focal_length(focal_length)
-                .resolution(width, height)
-                .sensor_width(1.0)
-                .build();
+        let message_label_clone = message_label.clone();
+        let progress_bar = progress_bar.clone();
+        let button = button.clone();
+
+        println!("All inputs are valid. Proceeding with rendering.");
+        message_label.set_markup(green_style);
+
+        // Schedule rendering to start after a short delay
+        glib::timeout_add_local(50, move || {
+            const OUTPUT_PATH: &str = "output.ppm";
+            let updated_scene = scene.clone();
+
+            // The render runs on its own thread and sends its progress back to the interface
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+            let progress_sender = std::sync::Mutex::new(sender.clone());
+
+            let mut builder = builder.clone();
+            builder.on_progress(move |progress| {
+                if let Ok(sender) = progress_sender.lock() {
+                    sender.send(RenderUpdate::Progress(*progress)).ok();
+                }
+            });
+            let mut camera = builder.build();
+
+            button.set_sensitive(false);
+            progress_bar.set_fraction(0.0);
+            receiver.attach(None, clone!(@strong message_label_clone, @strong progress_bar, @strong button => move |update| {
+                match update {
+                    RenderUpdate::Progress(progress) => {
+                        let eta = progress.eta.unwrap_or_default().as_secs();
+                        progress_bar.set_fraction(progress.done);
+                        progress_bar.set_text(Some(&format!(
+                            "{:.0}%, {}:{:02} left",
+                            progress.done * 100.0,
+                            eta / 60,
+                            eta % 60
+                        )));
+                        glib::Continue(true)
+                    }
+                    RenderUpdate::Done(stats) => {
+                        progress_bar.set_fraction(1.0);
+                        progress_bar.set_text(Some("Done"));
+                        // A summary of the statistics, with all of them when hovering it
+                        message_label_clone.set_markup(&format!(
+                            "<span foreground='green'>Rendering done: {:.1} M rays, {:.2} M per second, {:.2} rays per path.</span>",
+                            stats.rays() as f64 / 1e6,
+                            stats.rays_per_second() / 1e6,
+                            stats.average_path_length()
+                        ));
+                        message_label_clone.set_tooltip_text(Some(&stats.to_string()));
+                        button.set_sensitive(true);
+                        glib::Continue(false)
+                    }
+                    RenderUpdate::Failed(error) => {
+                        progress_bar.set_text(Some("Failed"));
+                        message_label_clone.set_markup(&format!(
+                            "<span foreground='red'>{}</span>",
+                            glib::markup_escape_text(&error.to_string())
+                        ));
+                        button.set_sensitive(true);
+                        glib::Continue(false)
+                    }
+                }
+            }));
 
+            std::thread::spawn(move || {
                 camera.send_rays(updated_scene);
-                camera.write_to_ppm(OUTPUT_PATH);
-                message_label_clone.set_markup(render_done_style);
-
-                glib::Continue(false)
-            }));
-        } else {
-            println!("Invalid input detected. Please enter numbers in 0.0 format");
-            message_label.set_markup(red_style);
-        }
+                let update = match camera.write_to_ppm(OUTPUT_PATH) {
+                    Ok(()) => RenderUpdate::Done(camera.stats),
+                    Err(error) => RenderUpdate::Failed(error),
+                };
+                sender.send(update).ok();
+            });
+
+            glib::Continue(false)
+        });
     }));
 
     window.connect_delete_event(|_, _| {
@@ -272,6 +418,7 @@ pub fn launch_gui() {
 
     window.show_all();
     gtk::main();
+    Ok(())
 }
 
 fn create_object_box(vertical_box: &Box) -> FlowBox {
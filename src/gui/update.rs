@@ -1,7 +1,8 @@
+use crate::config::textures::REFERENCE_WHITE_NITS;
 use crate::gui::AppState;
 use crate::objects::{Cube, Cylinder, FlatPlane, Objects, Sphere};
 use crate::raytracer::Scene;
-use crate::textures::Texture::*;
+use crate::textures::{Emission, Principled, Texture, Texture::*};
 use crate::type_aliases::Color;
 use gtk::{ColorChooserExt, ComboBoxTextExt, EntryExt};
 
@@ -50,7 +51,8 @@ pub fn update_scene_from_gui(app_state: Rc<RefCell<AppState>>) -> Scene {
         let sphere_texture = match material.as_str() {
             "Diffusive" => Diffusive(sphere_color),
             "Reflective" => Reflective,
-            "Light" => Light(sphere_color),
+            "Light" => Light(Emission::new(sphere_color, REFERENCE_WHITE_NITS)),
+            "Principled" => principled_texture(&sphere_config.principled.borrow(), sphere_color),
             // Add other cases as needed
             _ => Diffusive(sphere_color), // Default case
         };
@@ -103,7 +105,10 @@ pub fn update_scene_from_gui(app_state: Rc<RefCell<AppState>>) -> Scene {
         let cylinder_texture = match material.as_str() {
             "Diffusive" => Diffusive(cylinder_color),
             "Reflective" => Reflective,
-            "Light" => Light(cylinder_color),
+            "Light" => Light(Emission::new(cylinder_color, REFERENCE_WHITE_NITS)),
+            "Principled" => {
+                principled_texture(&cylinder_config.principled.borrow(), cylinder_color)
+            }
             // Add other cases as needed
             _ => Diffusive(cylinder_color), // Default case
         };
@@ -155,7 +160,8 @@ pub fn update_scene_from_gui(app_state: Rc<RefCell<AppState>>) -> Scene {
         let cube_texture = match material.as_str() {
             "Diffusive" => Diffusive(cube_color),
             "Reflective" => Reflective,
-            "Light" => Light(cube_color),
+            "Light" => Light(Emission::new(cube_color, REFERENCE_WHITE_NITS)),
+            "Principled" => principled_texture(&cube_config.principled.borrow(), cube_color),
             // Add other cases as needed
             _ => Diffusive(cube_color), // Default case
         };
@@ -202,7 +208,10 @@ pub fn update_scene_from_gui(app_state: Rc<RefCell<AppState>>) -> Scene {
         let flat_plane_texture = match material.as_str() {
             "Diffusive" => Diffusive(flat_plane_color),
             "Reflective" => Reflective,
-            "Light" => Light(flat_plane_color),
+            "Light" => Light(Emission::new(flat_plane_color, REFERENCE_WHITE_NITS)),
+            "Principled" => {
+                principled_texture(&flat_plane_config.principled.borrow(), flat_plane_color)
+            }
             // Add other cases as needed
             _ => Diffusive(flat_plane_color), // Default case
         };
@@ -217,6 +226,17 @@ pub fn update_scene_from_gui(app_state: Rc<RefCell<AppState>>) -> Scene {
 
     Scene {
         objects,
+        volumes: Vec::new(),
+        fog: None,
         brightness: app_state_borrowed.brightness,
     }
 }
+
+/// The material editor stores the emission as a grey strength, tint it with the object color
+fn principled_texture(material: &Principled, color: Color) -> Texture {
+    Texture::Principled(Principled {
+        base_color: color,
+        emission: material.emission.component_mul(&color) / 255.0,
+        ..*material
+    })
+}
@@ -1,81 +1,114 @@
-use crate::gui::{CubeConfig, CylinderConfig, FlatPlaneConfig, SphereConfig};
-use gtk::EntryExt;
+use crate::gui::AppState;
+use crate::raytracer::{Issue, Subject};
+use gtk::{Entry, EntryExt, EntryIconPosition};
 
-pub fn validate_spheres(spheres: &[SphereConfig]) -> bool {
-    for sphere in spheres {
-        let pos_x = sphere.pos_x_entry.borrow().get_text().to_string();
-        let pos_y = sphere.pos_y_entry.borrow().get_text().to_string();
-        let pos_z = sphere.pos_z_entry.borrow().get_text().to_string();
-        let radius = sphere.radius_entry.borrow().get_text().to_string();
+/// Entries of one object in the settings, with the field of the scene each of them sets
+struct ObjectEntries {
+    name: String,
+    entries: Vec<(&'static str, Entry)>,
+}
 
-        if !is_valid_number(&pos_x)
-            || !is_valid_number(&pos_y)
-            || !is_valid_number(&pos_z)
-            || !is_valid_number(&radius)
-        {
-            return false;
-        }
+/// Entries of the objects, in the order `update_scene_from_gui` adds the objects to the scene
+fn object_entries(app_state: &AppState) -> Vec<ObjectEntries> {
+    let mut objects = Vec::new();
+    for sphere in &app_state.spheres {
+        objects.push(ObjectEntries {
+            name: format!("Sphere {}", sphere.id.borrow()),
+            entries: vec![
+                ("center", sphere.pos_x_entry.borrow().clone()),
+                ("center", sphere.pos_y_entry.borrow().clone()),
+                ("center", sphere.pos_z_entry.borrow().clone()),
+                ("radius", sphere.radius_entry.borrow().clone()),
+            ],
+        });
+    }
+    for cylinder in &app_state.cylinders {
+        objects.push(ObjectEntries {
+            name: format!("Cylinder {}", cylinder.id.borrow()),
+            entries: vec![
+                ("center", cylinder.pos_x_entry.borrow().clone()),
+                ("center", cylinder.pos_y_entry.borrow().clone()),
+                ("center", cylinder.pos_z_entry.borrow().clone()),
+                ("radius", cylinder.radius_entry.borrow().clone()),
+                ("height", cylinder.height_entry.borrow().clone()),
+            ],
+        });
     }
-    true
+    for cube in &app_state.cubes {
+        objects.push(ObjectEntries {
+            name: format!("Cube {}", cube.id.borrow()),
+            entries: vec![
+                ("center", cube.pos_x_entry.borrow().clone()),
+                ("center", cube.pos_y_entry.borrow().clone()),
+                ("center", cube.pos_z_entry.borrow().clone()),
+                ("size", cube.radius_entry.borrow().clone()),
+            ],
+        });
+    }
+    for flat_plane in &app_state.flat_planes {
+        objects.push(ObjectEntries {
+            name: format!("Flat Plane {}", flat_plane.id.borrow()),
+            entries: vec![
+                ("center", flat_plane.pos_x_entry.borrow().clone()),
+                ("center", flat_plane.pos_y_entry.borrow().clone()),
+                ("center", flat_plane.pos_z_entry.borrow().clone()),
+                ("radius", flat_plane.radius_entry.borrow().clone()),
+            ],
+        });
+    }
+    objects
 }
 
-pub fn validate_cylinders(cylinders: &[CylinderConfig]) -> bool {
-    for cylinder in cylinders.iter() {
-        let pos_x = cylinder.pos_x_entry.borrow().get_text().to_string();
-        let pos_y = cylinder.pos_y_entry.borrow().get_text().to_string();
-        let pos_z = cylinder.pos_z_entry.borrow().get_text().to_string();
-        let radius = cylinder.radius_entry.borrow().get_text().to_string();
-        let height = cylinder.height_entry.borrow().get_text().to_string();
-
-        if !is_valid_number(&pos_x)
-            || !is_valid_number(&pos_y)
-            || !is_valid_number(&pos_z)
-            || !is_valid_number(&radius)
-            || !is_valid_number(&height)
-        {
-            return false;
+/// Object entries with text that is not a number. Empty entries are fine, they use the defaults.
+pub fn entry_issues(app_state: &AppState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for (index, object) in object_entries(app_state).into_iter().enumerate() {
+        for (field, entry) in object.entries {
+            let text = entry.get_text();
+            if !text.is_empty() && text.parse::<f64>().is_err() {
+                issues.push(Issue {
+                    subject: Subject::Object(index),
+                    field,
+                    reason: "must be a number",
+                });
+            }
         }
     }
-    true
+    issues
 }
 
-pub fn validate_cubes(cubes: &[CubeConfig]) -> bool {
-    for cube in cubes {
-        let pos_x = cube.pos_x_entry.borrow().get_text().to_string();
-        let pos_y = cube.pos_y_entry.borrow().get_text().to_string();
-        let pos_z = cube.pos_z_entry.borrow().get_text().to_string();
-        let radius = cube.radius_entry.borrow().get_text().to_string();
-
-        if !is_valid_number(&pos_x)
-            || !is_valid_number(&pos_y)
-            || !is_valid_number(&pos_z)
-            || !is_valid_number(&radius)
-        {
-            return false;
+/// Mark the object entries that `issues` are about, and clear the others
+pub fn mark_issues(app_state: &AppState, issues: &[Issue]) {
+    for (index, object) in object_entries(app_state).into_iter().enumerate() {
+        for (field, entry) in &object.entries {
+            let issue = issues
+                .iter()
+                .find(|issue| issue.subject == Subject::Object(index) && issue.field == *field);
+            mark_entry(entry, issue);
         }
     }
-    true
 }
 
-pub fn validate_flat_planes(flat_planes: &[FlatPlaneConfig]) -> bool {
-    for flat_plane in flat_planes {
-        let pos_x = flat_plane.pos_x_entry.borrow().get_text().to_string();
-        let pos_y = flat_plane.pos_y_entry.borrow().get_text().to_string();
-        let pos_z = flat_plane.pos_z_entry.borrow().get_text().to_string();
-        let radius = flat_plane.radius_entry.borrow().get_text().to_string();
-
-        if !is_valid_number(&pos_x)
-            || !is_valid_number(&pos_y)
-            || !is_valid_number(&pos_z)
-            || !is_valid_number(&radius)
-        {
-            return false;
-        }
-    }
-    true
+/// Show the reason of `issue` with an icon at the end of `entry`, or remove it with `None`
+pub fn mark_entry(entry: &Entry, issue: Option<&Issue>) {
+    let icon = issue.map(|_| "dialog-error");
+    let reason = issue.map(|issue| format!("{} {}", issue.field, issue.reason));
+    entry.set_icon_from_icon_name(EntryIconPosition::Secondary, icon);
+    entry.set_icon_tooltip_text(EntryIconPosition::Secondary, reason.as_deref());
 }
 
-// Function to validate position entries
-fn is_valid_number(input: &str) -> bool {
-    input.is_empty() || input.parse::<f64>().is_ok()
+/// `issue` with the name the object has in the settings, such as `Sphere 2: radius must be
+/// greater than 0`
+pub fn issue_message(app_state: &AppState, issue: &Issue) -> String {
+    let name = match issue.subject {
+        Subject::Object(index) => object_entries(app_state)
+            .into_iter()
+            .nth(index)
+            .map(|object| object.name),
+        _ => None,
+    };
+    match name {
+        Some(name) => format!("{name}: {} {}", issue.field, issue.reason),
+        None => issue.to_string(),
+    }
 }
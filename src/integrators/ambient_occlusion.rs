@@ -0,0 +1,59 @@
+use super::Integrator;
+use crate::color::RGB;
+use crate::config::integrators::AO_DISTANCE;
+use crate::config::rays::*;
+use crate::raytracer::Ray;
+use crate::textures::cosine_hemisphere;
+
+/// ### AmbientOcclusion
+///
+/// Shades every surface by how much of the sky above it is left open by the objects within
+/// `distance`. Materials and lights are ignored, which makes it a quick check of the geometry.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        Self { distance }
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self::new(AO_DISTANCE)
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color {
+        let Some((intersection, _)) = ray.closest_intersection(scene) else {
+            return Color::white();
+        };
+
+        let normal = intersection.normal;
+        let facing_normal = if ray.direction.dot(&normal) > 0.0 {
+            -normal
+        } else {
+            normal
+        };
+
+        let direction = facing_normal.to_world(&cosine_hemisphere(rng.gen(), rng.gen()));
+        let origin = intersection.hit_point + 1e-3 * facing_normal;
+
+        if ray.occluded(scene, origin, direction, self.distance) {
+            Color::black()
+        } else {
+            Color::white()
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "ao"
+    }
+
+    fn settings(&self) -> Vec<f64> {
+        vec![self.distance]
+    }
+}
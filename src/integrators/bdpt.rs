@@ -0,0 +1,463 @@
+use super::Integrator;
+use crate::config::rays::*;
+use crate::objects::Object;
+use crate::raytracer::{FirstHit, Ray};
+use crate::textures::{cosine_hemisphere, Texture};
+use std::f64::consts::PI;
+
+/// ### BidirectionalPathTracer
+///
+/// Traces one subpath from the camera and one from a random light, then connects every vertex of
+/// the first with every vertex of the second. All the ways of building the same path are
+/// combined with the power heuristic, so light that only reaches the camera through small gaps or
+/// behind glass is found much sooner than with the `PathTracer`.
+///
+/// Light subpaths are never connected to the camera itself, since an integrator only computes the
+/// light along its own camera ray. Volumes and fog dim the connections but never scatter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BidirectionalPathTracer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// Point where a subpath started or scattered
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    kind: VertexKind,
+    point: Point,
+    normal: Normal,
+    texture: Option<Texture>,
+    /// Light carried to this vertex, divided by the density of the subpath so far
+    beta: Color,
+    /// Density of sampling this vertex from the previous vertex of its subpath, per unit area
+    pdf_fwd: f64,
+    /// Density of sampling this vertex from the next vertex, as if the subpath ran the other way
+    pdf_rev: f64,
+    /// Density of picking this point to start a light subpath, 0.0 on anything but area lights
+    light_pdf: f64,
+    delta: bool,
+}
+
+impl Vertex {
+    fn camera(point: Point) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            point,
+            normal: Normal::zeros(),
+            texture: None,
+            beta: Color::repeat(1.0),
+            pdf_fwd: 1.0,
+            pdf_rev: 0.0,
+            light_pdf: 0.0,
+            delta: false,
+        }
+    }
+
+    /// Whether the BSDF can be evaluated for directions picked by a connection
+    fn connectible(&self) -> bool {
+        self.texture
+            .is_some_and(|texture| texture.has_non_delta_lobe())
+    }
+
+    fn emission(&self) -> Color {
+        self.texture
+            .and_then(|texture| texture.emission())
+            .unwrap_or_default()
+    }
+
+    /// Turn the density of sampling the direction towards `next` into a density per unit area
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let to_next = next.point - self.point;
+        let distance_squared = to_next.norm_squared();
+        if distance_squared <= 0.0 {
+            return 0.0;
+        }
+
+        let cos = if next.kind == VertexKind::Camera {
+            1.0
+        } else {
+            next.normal.dot(&to_next).abs() / distance_squared.sqrt()
+        };
+        pdf * cos / distance_squared
+    }
+
+    /// Density per unit area of this vertex sampling `next`, when the subpath came from `previous`
+    fn pdf(&self, previous: Option<&Vertex>, next: &Vertex) -> f64 {
+        let wi = (next.point - self.point).normalize();
+
+        let pdf = match (self.kind, previous) {
+            (VertexKind::Light, _) => emission_pdf(self.normal, wi),
+            (VertexKind::Surface, Some(previous)) => {
+                let wo = (previous.point - self.point).normalize();
+                self.texture
+                    .map_or(0.0, |texture| texture.pdf(wo, wi, self.normal))
+            }
+            _ => 0.0,
+        };
+        self.convert_density(pdf, next)
+    }
+}
+
+impl Integrator for BidirectionalPathTracer {
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color {
+        let max_vertices = ray.max_depth as usize + 1;
+
+        let mut camera_path = Vec::with_capacity(max_vertices);
+        camera_path.push(Vertex::camera(ray.origin));
+        let mut radiance = random_walk(
+            scene,
+            ray.origin,
+            ray.direction,
+            1.0,
+            Color::repeat(1.0),
+            &mut camera_path,
+            max_vertices,
+            &mut ray.first_hit,
+            rng,
+        );
+
+        let mut light_path = Vec::with_capacity(max_vertices);
+        light_subpath(scene, &mut light_path, max_vertices, rng);
+
+        for t in 2..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                if s + t - 2 > ray.max_depth as usize {
+                    break;
+                }
+                radiance += connect(scene, ray, &mut light_path, &mut camera_path, s, t, rng);
+            }
+        }
+
+        radiance
+    }
+
+    fn name(&self) -> &'static str {
+        "bdpt"
+    }
+}
+
+/// Start a subpath on a random light, and follow it through the scene
+fn light_subpath<R: Rng>(scene: &Scene, path: &mut Vec<Vertex>, max_vertices: usize, rng: &mut R) {
+    let light_count = scene.lights().count();
+    if light_count == 0 {
+        return;
+    }
+
+    let light = scene
+        .lights()
+        .nth(rng.gen_range(0..light_count))
+        .expect("Light index is in range");
+    let Some((point, normal)) = light.sample_surface(rng) else {
+        return;
+    };
+
+    // Lights shine from both sides, pick one and send the light out around its normal
+    let side = if rng.gen::<bool>() { normal } else { -normal };
+    let direction = side.to_world(&cosine_hemisphere(rng.gen(), rng.gen()));
+    let direction_pdf = emission_pdf(normal, direction);
+    let light_pdf = origin_pdf(light.as_ref(), light_count);
+
+    let emission = light.texture().emission().unwrap_or_default();
+    let vertex = Vertex {
+        kind: VertexKind::Light,
+        point,
+        normal,
+        texture: Some(light.texture()),
+        beta: Color::repeat(1.0 / light_pdf),
+        pdf_fwd: light_pdf,
+        pdf_rev: 0.0,
+        light_pdf,
+        delta: false,
+    };
+    path.push(vertex);
+
+    let cos = normal.dot(&direction).abs();
+    let beta = vertex.beta.component_mul(&emission) * cos / direction_pdf;
+    let origin = point + 1e-3 * side;
+
+    random_walk(
+        scene,
+        origin,
+        direction,
+        direction_pdf,
+        beta,
+        path,
+        max_vertices,
+        &mut None,
+        rng,
+    );
+}
+
+/// Extend `path` by sampling the surfaces it hits, until it leaves the scene, hits a light or is
+/// terminated by russian roulette. Returns the background light carried by a path that escapes.
+/// The first surface the path hits goes to `first_hit`, for the AOVs of camera subpaths.
+#[allow(clippy::too_many_arguments)]
+fn random_walk<R: Rng>(
+    scene: &Scene,
+    mut origin: Point,
+    mut direction: Direction,
+    mut pdf: f64,
+    mut beta: Color,
+    path: &mut Vec<Vertex>,
+    max_vertices: usize,
+    first_hit: &mut Option<FirstHit>,
+    rng: &mut R,
+) -> Color {
+    let light_count = scene.lights().count();
+    let mut throughput = Color::repeat(1.0);
+
+    while path.len() < max_vertices {
+        let mut ray = Ray::new(origin, direction, 0);
+        let closest = ray.closest_intersection(scene);
+        if path.len() == 1 {
+            *first_hit = ray.first_hit;
+        }
+        let Some((intersection, object)) = closest else {
+            // Only camera subpaths see the background
+            return if path[0].kind == VertexKind::Camera {
+                beta.component_mul(&scene.background())
+            } else {
+                Color::zeros()
+            };
+        };
+
+        let texture = intersection.texture;
+        let normal = intersection.normal;
+        let previous = path.last().expect("Subpaths start with a vertex");
+
+        let mut vertex = Vertex {
+            kind: VertexKind::Surface,
+            point: intersection.hit_point,
+            normal,
+            texture: Some(texture),
+            beta,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            light_pdf: origin_pdf(object.as_ref(), light_count),
+            delta: false,
+        };
+        vertex.pdf_fwd = previous.convert_density(pdf, &vertex);
+
+        // Lights absorb everything
+        if let Texture::Light(_) = texture {
+            path.push(vertex);
+            break;
+        }
+
+        let Some(sample) = texture.sample(direction, normal, rng) else {
+            path.push(vertex);
+            break;
+        };
+        if sample.direction.near_zero() {
+            path.push(vertex);
+            break;
+        }
+
+        let wo = -direction;
+        vertex.delta = sample.delta;
+        pdf = if sample.delta { 0.0 } else { sample.pdf };
+        let pdf_rev = if sample.delta {
+            0.0
+        } else {
+            texture.pdf(sample.direction, wo, normal)
+        };
+
+        path.push(vertex);
+        let count = path.len();
+        path[count - 2].pdf_rev = vertex.convert_density(pdf_rev, &path[count - 2]);
+
+        beta = beta.component_mul(&sample.weight);
+        throughput = throughput.component_mul(&sample.weight);
+        if count as u32 > ROULETTE_DEPTH {
+            let survival = throughput.max().min(0.95);
+            if rng.gen::<f64>() >= survival {
+                break;
+            }
+            beta /= survival;
+            throughput /= survival;
+        }
+
+        origin = vertex.point + 1e-3 * normal * sample.direction.dot(&normal).signum();
+        direction = sample.direction;
+    }
+
+    Color::zeros()
+}
+
+/// Light carried by the path made of the first `s` light vertices and the first `t` camera
+/// vertices, weighted against every other way of sampling it
+fn connect<R: Rng>(
+    scene: &Scene,
+    ray: &Ray,
+    light_path: &mut [Vertex],
+    camera_path: &mut [Vertex],
+    s: usize,
+    t: usize,
+    rng: &mut R,
+) -> Color {
+    let camera_vertex = camera_path[t - 1];
+
+    let contribution = if s == 0 {
+        camera_vertex.beta.component_mul(&camera_vertex.emission())
+    } else {
+        let light_vertex = light_path[s - 1];
+        if !camera_vertex.connectible() {
+            return Color::zeros();
+        }
+        if s > 1 && !light_vertex.connectible() {
+            return Color::zeros();
+        }
+
+        let to_light = light_vertex.point - camera_vertex.point;
+        let distance = to_light.norm();
+        let wi = to_light / distance;
+
+        let camera_wo = (camera_path[t - 2].point - camera_vertex.point).normalize();
+        let camera_factor = camera_vertex.texture.map_or(Color::zeros(), |texture| {
+            texture.eval(camera_wo, wi, camera_vertex.normal)
+        });
+
+        let light_factor = if s == 1 {
+            light_vertex.emission()
+        } else {
+            let light_wi = (light_path[s - 2].point - light_vertex.point).normalize();
+            light_vertex.texture.map_or(Color::zeros(), |texture| {
+                texture.eval(-wi, light_wi, light_vertex.normal)
+            })
+        };
+
+        let cos_camera = camera_vertex.normal.dot(&wi).abs();
+        let cos_light = light_vertex.normal.dot(&wi).abs();
+        let unoccluded = camera_factor
+            .component_mul(&light_factor)
+            .component_mul(&camera_vertex.beta)
+            .component_mul(&light_vertex.beta)
+            * cos_camera
+            * cos_light
+            / (distance * distance);
+        if unoccluded.max() <= 0.0 {
+            return Color::zeros();
+        }
+
+        let origin = camera_vertex.point
+            + 1e-3 * camera_vertex.normal * wi.dot(&camera_vertex.normal).signum();
+        let target =
+            light_vertex.point - 1e-3 * light_vertex.normal * wi.dot(&light_vertex.normal).signum();
+        let span = target - origin;
+        let span_distance = span.norm();
+        unoccluded * ray.visibility(scene, origin, span / span_distance, span_distance, rng)
+    };
+
+    if contribution.max() <= 0.0 {
+        return Color::zeros();
+    }
+    contribution * mis_weight(light_path, camera_path, s, t)
+}
+
+/// Power heuristic weight of the strategy with `s` light vertices and `t` camera vertices.
+///
+/// The densities at the connection change for every strategy, so they are set for this one and
+/// restored afterwards.
+fn mis_weight(light_path: &mut [Vertex], camera_path: &mut [Vertex], s: usize, t: usize) -> f64 {
+    if s + t == 2 {
+        return 1.0;
+    }
+
+    let camera_vertex = camera_path[t - 1];
+    let camera_previous = camera_path[t - 2];
+    let light_vertex = (s > 0).then(|| light_path[s - 1]);
+    let light_previous = (s > 1).then(|| light_path[s - 2]);
+
+    // Emitters that can not start light subpaths are only ever found by the camera subpath
+    if s == 0 && camera_vertex.light_pdf <= 0.0 {
+        return 1.0;
+    }
+
+    let saved_camera = (camera_path[t - 1].pdf_rev, camera_path[t - 2].pdf_rev);
+    let saved_light = (
+        light_vertex.map(|v| v.pdf_rev),
+        light_previous.map(|v| v.pdf_rev),
+    );
+
+    camera_path[t - 1].pdf_rev = match light_vertex {
+        Some(light_vertex) => light_vertex.pdf(light_previous.as_ref(), &camera_vertex),
+        None => camera_vertex.light_pdf,
+    };
+    camera_path[t - 2].pdf_rev = match light_vertex {
+        Some(light_vertex) => camera_vertex.pdf(Some(&light_vertex), &camera_previous),
+        None => {
+            let direction = (camera_previous.point - camera_vertex.point).normalize();
+            let pdf = emission_pdf(camera_vertex.normal, direction);
+            camera_vertex.convert_density(pdf, &camera_previous)
+        }
+    };
+    if let Some(light_vertex) = light_vertex {
+        light_path[s - 1].pdf_rev = camera_vertex.pdf(Some(&camera_previous), &light_vertex);
+        if let Some(light_previous) = light_previous {
+            light_path[s - 2].pdf_rev = light_vertex.pdf(Some(&camera_vertex), &light_previous);
+        }
+    }
+
+    // The vertices joined by the connection are never treated as delta vertices
+    let delta = |vertex: &Vertex, index: usize, last: usize| vertex.delta && index != last;
+    let ratio = |vertex: &Vertex| {
+        let ratio = remap_zero(vertex.pdf_rev) / remap_zero(vertex.pdf_fwd);
+        ratio * ratio
+    };
+
+    let mut sum = 0.0;
+    let mut ri = 1.0;
+    // Strategies with fewer camera vertices, down to the last one that still leaves a surface
+    for i in (2..t).rev() {
+        ri *= ratio(&camera_path[i]);
+        if !delta(&camera_path[i], i, t - 1) && !delta(&camera_path[i - 1], i - 1, t - 1) {
+            sum += ri;
+        }
+    }
+
+    ri = 1.0;
+    for i in (0..s).rev() {
+        ri *= ratio(&light_path[i]);
+        let previous_delta = i > 0 && delta(&light_path[i - 1], i - 1, s - 1);
+        if !delta(&light_path[i], i, s - 1) && !previous_delta {
+            sum += ri;
+        }
+    }
+
+    camera_path[t - 1].pdf_rev = saved_camera.0;
+    camera_path[t - 2].pdf_rev = saved_camera.1;
+    if let Some(pdf_rev) = saved_light.0 {
+        light_path[s - 1].pdf_rev = pdf_rev;
+    }
+    if let Some(pdf_rev) = saved_light.1 {
+        light_path[s - 2].pdf_rev = pdf_rev;
+    }
+
+    1.0 / (1.0 + sum)
+}
+
+/// Density of a two-sided area light sending its light along `direction`
+fn emission_pdf(normal: Normal, direction: Direction) -> f64 {
+    normal.dot(&direction).abs() / (2.0 * PI)
+}
+
+/// Density of starting a light subpath at a point on `object`, per unit area
+fn origin_pdf(object: &dyn Object, light_count: usize) -> f64 {
+    if object.texture().emission().is_none() || object.area() <= 0.0 || light_count == 0 {
+        return 0.0;
+    }
+    1.0 / (object.area() * light_count as f64)
+}
+
+/// Treat missing densities of delta vertices as 1.0, so they cancel out in the ratios
+fn remap_zero(pdf: f64) -> f64 {
+    if pdf == 0.0 {
+        1.0
+    } else {
+        pdf
+    }
+}
@@ -0,0 +1,72 @@
+use super::Integrator;
+use crate::color::RGB;
+use crate::config::rays::*;
+use crate::raytracer::Ray;
+
+/// ### DebugView
+///
+/// Shows a single property of the first surface each camera ray hits, to diagnose scenes
+/// without waiting for the light to converge. Rays that hit nothing are black.
+///
+/// - `Normals` maps the normal from the -1.0-1.0 range to a color
+/// - `Depth` is white close to the camera and fades to black at `max_distance`
+/// - `Uv` shows the texture coordinates in the red and green channels
+/// - `ObjectId` gives every object in `Scene::objects` its own color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugView {
+    Normals,
+    Depth { max_distance: f64 },
+    Uv,
+    ObjectId,
+}
+
+impl Integrator for DebugView {
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, _rng: &mut Samples) -> Color {
+        let Some((intersection, object)) = ray.closest_intersection(scene) else {
+            return Color::black();
+        };
+
+        match self {
+            DebugView::Normals => (intersection.normal + Vector3::repeat(1.0)) * 0.5 * 255.0,
+            DebugView::Depth { max_distance } => {
+                let closeness = 1.0 - (intersection.distance / max_distance).clamp(0.0, 1.0);
+                Color::white() * closeness
+            }
+            DebugView::Uv => {
+                let (u, v) = object.uv(&intersection.hit_point);
+                Color::new(u, v, 0.0) * 255.0
+            }
+            DebugView::ObjectId => {
+                let index = scene
+                    .objects
+                    .iter()
+                    .position(|candidate| Arc::ptr_eq(candidate, object))
+                    .unwrap_or_default();
+                id_color(index)
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            DebugView::Normals => "normals",
+            DebugView::Depth { .. } => "depth",
+            DebugView::Uv => "uv",
+            DebugView::ObjectId => "object-id",
+        }
+    }
+
+    fn settings(&self) -> Vec<f64> {
+        match self {
+            DebugView::Depth { max_distance } => vec![*max_distance],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Bright color that stays the same for an index between renders
+pub(crate) fn id_color(index: usize) -> Color {
+    let hash = (index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let channel = |shift: u64| 64.0 + ((hash >> shift) & 0xFF) as f64 * 0.75;
+    Color::new(channel(16), channel(32), channel(48))
+}
@@ -0,0 +1,143 @@
+use super::{Integrator, PhotonMap};
+use crate::config::rays::*;
+use crate::raytracer::Ray;
+use crate::textures::Texture;
+
+/// ### PathTracer
+///
+/// Unidirectional path tracer. Follows the ray bounce by bounce: finds the closest intersection,
+/// samples the lights, and continues in a direction sampled from the surface or medium.
+///
+/// The ray itself is moved along the path, so no secondary rays are allocated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color {
+        self.trace(ray, scene, None, rng);
+        ray.radiance
+    }
+
+    fn name(&self) -> &'static str {
+        "path"
+    }
+}
+
+impl PathTracer {
+    /// Follow the path of `ray`, adding the light it finds to `ray.radiance`.
+    ///
+    /// With `caustics`, every diffuse or glossy surface also gathers the photons around it. Light
+    /// reaching such a surface through mirrors and glass is then already accounted for, so it is
+    /// skipped when the path itself finds it.
+    pub(crate) fn trace<R: Rng>(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        caustics: Option<&PhotonMap>,
+        rng: &mut R,
+    ) {
+        // Whether the last diffuse or glossy bounce gathered photons, and whether the path went
+        // through mirrors or glass since then
+        let mut gathered = false;
+        let mut behind_caustics = false;
+
+        while !ray.reached_max_depth() {
+            let closest = ray.closest_intersection(scene);
+
+            // Particles in fog and volumes can scatter the ray before it reaches the surface
+            if let Some((distance, medium)) = ray.sample_media(scene, rng) {
+                let point = ray.origin + ray.direction * distance;
+                let incoming = ray.direction;
+                let albedo = medium.albedo / 255.0;
+
+                let direct = albedo.component_mul(&ray.direct_light(
+                    scene,
+                    point,
+                    |wi| {
+                        let phase = medium.phase(incoming, wi);
+                        (Color::repeat(phase), phase)
+                    },
+                    rng,
+                ));
+                ray.add_radiance(direct);
+
+                let direction = medium.sample_phase(ray.direction, rng);
+                let pdf = medium.phase(incoming, direction);
+                gathered = false;
+                behind_caustics = false;
+                if !ray.bounce(point, direction, Some(pdf), albedo, rng) {
+                    return;
+                }
+                continue;
+            }
+
+            let Some((intersection, object)) = closest else {
+                ray.add_emission(scene.background());
+                return;
+            };
+
+            let texture = intersection.texture;
+            let normal = intersection.normal;
+            let hit_point = intersection.hit_point;
+
+            if let Some(emission) = texture.emission().filter(|_| !behind_caustics) {
+                let weight = ray.emission_weight(scene, object.as_ref(), &intersection);
+                ray.add_emission(emission * weight);
+            }
+
+            // Lights do not reflect anything
+            if let Texture::Light(_) = texture {
+                return;
+            }
+
+            let wo = -ray.direction;
+            if texture.has_non_delta_lobe() {
+                let offset_point = hit_point + 1e-3 * normal * wo.dot(&normal).signum();
+                let direct = ray.direct_light(
+                    scene,
+                    offset_point,
+                    |wi| {
+                        let cos = wi.dot(&normal).abs();
+                        (
+                            texture.eval(wo, wi, normal) * cos,
+                            texture.pdf(wo, wi, normal),
+                        )
+                    },
+                    rng,
+                );
+                ray.add_radiance(direct);
+
+                if let Some(caustics) = caustics {
+                    let caustic = caustics.estimate(hit_point, wo, normal, &texture);
+                    ray.add_radiance(caustic);
+                }
+            }
+
+            let Some(sample) = texture.sample(ray.direction, normal, rng) else {
+                return;
+            };
+            if sample.direction.near_zero() {
+                return;
+            }
+
+            // Transmitted rays continue on the other side of the surface
+            let offset = 1e-3 * normal * sample.direction.dot(&normal).signum();
+            let pdf = (!sample.delta).then_some(sample.pdf);
+            if sample.delta {
+                behind_caustics = gathered;
+            } else {
+                gathered = caustics.is_some() && texture.has_non_delta_lobe();
+                behind_caustics = false;
+            }
+            if !ray.bounce(
+                hit_point + offset,
+                sample.direction,
+                pdf,
+                sample.weight,
+                rng,
+            ) {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use super::{Integrator, PathTracer};
+use crate::config::integrators::{PHOTON_COUNT, PHOTON_RADIUS};
+use crate::config::rays::*;
+use crate::random::seeded_rng;
+use crate::raytracer::Ray;
+use crate::textures::{cosine_hemisphere, Texture};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::sync::RwLock;
+
+/// ### PhotonMapper
+///
+/// Path tracer that gets its caustics from a photon map. Before every render, photons are shot
+/// from the lights, and the ones that reach a diffuse or glossy surface through mirrors or glass
+/// are stored. Paths gather the photons within `radius` wherever they hit such a surface.
+///
+/// A smaller `radius` gives sharper but noisier caustics. Progressive mode shoots `passes` photon
+/// maps with a shrinking radius and spreads the samples over them, which removes the blur as the
+/// amount of passes grows.
+#[derive(Debug)]
+pub struct PhotonMapper {
+    pub photon_count: usize,
+    pub radius: f64,
+    pub passes: usize,
+    maps: RwLock<Vec<PhotonMap>>,
+}
+
+impl PhotonMapper {
+    pub fn new(photon_count: usize, radius: f64) -> Self {
+        Self {
+            photon_count,
+            radius,
+            passes: 1,
+            maps: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Use `passes` photon maps, each one with a smaller radius than the one before
+    pub fn progressive(mut self, passes: usize) -> Self {
+        self.passes = passes.max(1);
+        self
+    }
+
+    /// Radius of the photon map of `pass`. Every pass keeps two thirds of the new photons, like
+    /// progressive photon mapping.
+    fn pass_radius(&self, pass: usize) -> f64 {
+        let alpha = 2.0 / 3.0;
+        let mut radius_squared = self.radius * self.radius;
+        for i in 1..=pass {
+            radius_squared *= (i as f64 + alpha) / (i as f64 + 1.0);
+        }
+        radius_squared.sqrt()
+    }
+}
+
+impl Default for PhotonMapper {
+    fn default() -> Self {
+        Self::new(PHOTON_COUNT, PHOTON_RADIUS)
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn prepare(&self, scene: &Scene, seed: u64) {
+        let maps = (0..self.passes)
+            .into_par_iter()
+            .map(|pass| {
+                let photons = shoot_photons(scene, self.photon_count, seed, pass);
+                PhotonMap::new(photons, self.pass_radius(pass))
+            })
+            .collect();
+
+        *self.maps.write().expect("Photon maps are not poisoned") = maps;
+    }
+
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color {
+        let maps = self.maps.read().expect("Photon maps are not poisoned");
+        let map = match maps.len() {
+            0 => None,
+            count => maps.get(rng.gen_range(0..count)),
+        };
+
+        PathTracer.trace(ray, scene, map, rng);
+        ray.radiance
+    }
+
+    fn name(&self) -> &'static str {
+        match self.passes {
+            1 => "photon",
+            _ => "ppm",
+        }
+    }
+
+    fn settings(&self) -> Vec<f64> {
+        let mut settings = vec![self.photon_count as f64, self.radius];
+        if self.passes > 1 {
+            settings.push(self.passes as f64);
+        }
+        settings
+    }
+}
+
+/// Light that reached a surface after bouncing off at least one mirror or going through glass
+#[derive(Debug, Clone, Copy)]
+pub struct Photon {
+    pub position: Point,
+    /// Direction the photon came from, pointing away from the surface
+    pub incoming: Direction,
+    pub power: Color,
+    /// Axis the kd-tree splits on at this photon
+    axis: usize,
+}
+
+/// ### PhotonMap
+///
+/// Photons stored in a balanced kd-tree. The tree is implicit: the photon in the middle of any
+/// range splits the rest of the range along its `axis`.
+#[derive(Debug, Clone)]
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    radius: f64,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>, radius: f64) -> Self {
+        build(&mut photons);
+        Self { photons, radius }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Light reflected towards `wo` by the photons within the radius of `point`
+    pub fn estimate(
+        &self,
+        point: Point,
+        wo: Direction,
+        normal: Normal,
+        texture: &Texture,
+    ) -> Color {
+        let mut total = Color::zeros();
+        self.for_each_within(point, |photon| {
+            total += texture
+                .eval(wo, photon.incoming, normal)
+                .component_mul(&photon.power);
+        });
+        total / (PI * self.radius * self.radius)
+    }
+
+    fn for_each_within<F: FnMut(&Photon)>(&self, point: Point, mut visit: F) {
+        let radius_squared = self.radius * self.radius;
+        let mut stack = [(0, 0); 64];
+        let mut depth = 0;
+        if !self.photons.is_empty() {
+            stack[0] = (0, self.photons.len());
+            depth = 1;
+        }
+
+        while depth > 0 {
+            depth -= 1;
+            let (start, end) = stack[depth];
+            let middle = (start + end) / 2;
+            let photon = &self.photons[middle];
+
+            if (photon.position - point).norm_squared() <= radius_squared {
+                visit(photon);
+            }
+
+            let offset = point[photon.axis] - photon.position[photon.axis];
+            let (near, far) = if offset <= 0.0 {
+                ((start, middle), (middle + 1, end))
+            } else {
+                ((middle + 1, end), (start, middle))
+            };
+
+            if far.0 < far.1 && offset * offset <= radius_squared {
+                stack[depth] = far;
+                depth += 1;
+            }
+            if near.0 < near.1 {
+                stack[depth] = near;
+                depth += 1;
+            }
+        }
+    }
+}
+
+/// Sort `photons` into an implicit kd-tree, splitting every range along its widest axis
+fn build(photons: &mut [Photon]) {
+    if photons.len() <= 1 {
+        if let Some(photon) = photons.first_mut() {
+            photon.axis = 0;
+        }
+        return;
+    }
+
+    let (min, max) = photons.iter().fold(
+        (Point::repeat(f64::MAX), Point::repeat(f64::MIN)),
+        |(min, max), photon| (min.inf(&photon.position), max.sup(&photon.position)),
+    );
+    let axis = (max - min).imax();
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    photons[middle].axis = axis;
+
+    let (lower, upper) = photons.split_at_mut(middle);
+    build(lower);
+    build(&mut upper[1..]);
+}
+
+/// Shoot `count` photons from the lights, keeping the ones that end up on a diffuse or glossy
+/// surface through mirrors and glass
+fn shoot_photons(scene: &Scene, count: usize, seed: u64, pass: usize) -> Vec<Photon> {
+    let light_count = scene.lights().count();
+    if light_count == 0 || count == 0 {
+        return Vec::new();
+    }
+
+    (0..count)
+        .into_par_iter()
+        .map(|index| {
+            // Photon streams count down from the top, away from the streams of the pixels
+            let mut rng = seeded_rng(seed, u64::MAX - pass as u64, index as u64);
+            trace_photon(scene, light_count, count, &mut rng)
+        })
+        .flatten()
+        .collect()
+}
+
+/// Follow a single photon, returning every point it was stored at
+fn trace_photon<R: Rng>(
+    scene: &Scene,
+    light_count: usize,
+    count: usize,
+    rng: &mut R,
+) -> Vec<Photon> {
+    let mut photons = Vec::new();
+    let light = scene
+        .lights()
+        .nth(rng.gen_range(0..light_count))
+        .expect("Light index is in range");
+    let Some((point, normal)) = light.sample_surface(rng) else {
+        return photons;
+    };
+
+    // Lights shine from both sides with a cosine distribution, which cancels out the cosine of
+    // the emitted light and leaves the area of both sides over the amount of photons
+    let side = if rng.gen::<bool>() { normal } else { -normal };
+    let direction = side.to_world(&cosine_hemisphere(rng.gen(), rng.gen()));
+    let emission = light.texture().emission().unwrap_or_default();
+    let mut power = emission * 2.0 * PI * light.area() * light_count as f64 / count as f64;
+
+    let mut ray = Ray::new(point + 1e-3 * side, direction, 0);
+    let mut specular = false;
+
+    while !ray.reached_max_depth() {
+        let Some((intersection, _)) = ray.closest_intersection(scene) else {
+            return photons;
+        };
+
+        let texture = intersection.texture;
+        if let Texture::Light(_) = texture {
+            return photons;
+        }
+
+        let normal = intersection.normal;
+        if specular && texture.has_non_delta_lobe() {
+            photons.push(Photon {
+                position: intersection.hit_point,
+                incoming: -ray.direction,
+                power,
+                axis: 0,
+            });
+        }
+
+        // Only mirrors and glass keep the photon a caustic photon
+        let Some(sample) = texture.sample(ray.direction, normal, rng) else {
+            return photons;
+        };
+        if !sample.delta || sample.direction.near_zero() {
+            return photons;
+        }
+
+        power = power.component_mul(&sample.weight);
+        specular = true;
+
+        let offset = 1e-3 * normal * sample.direction.dot(&normal).signum();
+        let depth = ray.depth + 1;
+        ray = Ray::new(intersection.hit_point + offset, sample.direction, depth);
+    }
+
+    photons
+}
@@ -0,0 +1,165 @@
+use super::Integrator;
+use crate::config::rays::*;
+use crate::raytracer::Ray;
+use crate::textures::{dielectric_fresnel, refract, Principled, Texture};
+
+/// ### Whitted
+///
+/// Classic recursive ray tracer. Surfaces are lit directly by every light, with the background
+/// as ambient light. Only mirrors and glass spawn new rays, so indirect light between diffuse
+/// surfaces is missing, but images come out almost free of noise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Whitted;
+
+impl Integrator for Whitted {
+    fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color {
+        self.trace(ray, scene, rng)
+    }
+
+    fn name(&self) -> &'static str {
+        "whitted"
+    }
+}
+
+impl Whitted {
+    fn trace<R: Rng>(&self, ray: &mut Ray, scene: &Scene, rng: &mut R) -> Color {
+        if ray.reached_max_depth() {
+            return Color::zeros();
+        }
+
+        let Some((intersection, _)) = ray.closest_intersection(scene) else {
+            return scene.background();
+        };
+
+        let texture = intersection.texture;
+        let emission = texture.emission().unwrap_or_default();
+        if let Texture::Light(_) = texture {
+            return emission;
+        }
+
+        let normal = intersection.normal;
+        let hit_point = intersection.hit_point;
+        let wo = -ray.direction;
+        let offset_point = hit_point + 1e-3 * normal * wo.dot(&normal).signum();
+
+        let mut color = emission + albedo(&texture).component_mul(&scene.background());
+
+        // Direct light, one point on every light
+        for light in scene.lights() {
+            let Some((light_point, light_normal)) = light.sample_surface(rng) else {
+                continue;
+            };
+
+            let to_light = light_point - offset_point;
+            let distance = to_light.norm();
+            let wi = to_light / distance;
+            let cos_light = light_normal.dot(&wi).abs();
+            let factor = texture.eval(wo, wi, normal) * wi.dot(&normal).abs();
+            if cos_light <= 1e-6 || factor.max() <= 0.0 {
+                continue;
+            }
+
+            let emission = light.texture().emission().unwrap_or_default();
+            let visibility = ray.visibility(scene, offset_point, wi, distance * (1.0 - 1e-4), rng);
+            let solid_angle = cos_light * light.area() / (distance * distance);
+            color += factor.component_mul(&emission) * visibility * solid_angle;
+        }
+
+        let reflected = ray.direction - 2.0 * ray.direction.dot(&normal) * normal;
+        let reflection_origin = hit_point + 1e-3 * normal * reflected.dot(&normal).signum();
+
+        match texture {
+            Texture::Reflective => {
+                color += self.spawn(ray, scene, reflection_origin, reflected, rng);
+            }
+            Texture::Principled(material) => {
+                let (reflectance, transmittance, refracted) = split(&material, wo, normal);
+
+                if reflectance.max() > 1e-3 {
+                    let light = self.spawn(ray, scene, reflection_origin, reflected, rng);
+                    color += reflectance.component_mul(&light);
+                }
+
+                if let Some(refracted) = refracted.filter(|_| transmittance.max() > 1e-3) {
+                    let origin = hit_point + 1e-3 * normal * refracted.dot(&normal).signum();
+                    let light = self.spawn(ray, scene, origin, refracted, rng);
+                    color += transmittance.component_mul(&light);
+                }
+            }
+            _ => {}
+        }
+
+        color
+    }
+
+    /// Trace a secondary ray one bounce deeper than `ray`
+    fn spawn<R: Rng>(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        origin: Point,
+        direction: Direction,
+        rng: &mut R,
+    ) -> Color {
+        let mut secondary_ray = Ray::new(origin, direction, ray.depth + 1);
+        secondary_ray.max_depth = ray.max_depth;
+        self.trace(&mut secondary_ray, scene, rng)
+    }
+}
+
+/// Share of the diffuse lobes lit by the background, as a 0.0-1.0 factor
+fn albedo(texture: &Texture) -> Color {
+    match texture {
+        Texture::Diffusive(color) => color / 255.0,
+        Texture::Principled(material) => {
+            material.base_color / 255.0 * (1.0 - material.metallic) * (1.0 - material.transmission)
+        }
+        Texture::Reflective | Texture::Light(_) => Color::zeros(),
+    }
+}
+
+/// Weights of the perfect mirror reflection and refraction of a principled surface, and the
+/// refracted direction. Rough surfaces reflect less, since their highlights come from `eval`.
+fn split(
+    material: &Principled,
+    wo: Direction,
+    normal: Normal,
+) -> (Color, Color, Option<Direction>) {
+    let smoothness = (1.0 - material.roughness).powi(2);
+    let cos = wo.dot(&normal).abs();
+
+    let f0 = material.specular_tint();
+    let schlick = (1.0 - cos).clamp(0.0, 1.0).powi(5);
+    let mut reflectance = (f0 + (Color::repeat(1.0) - f0) * schlick) * smoothness;
+
+    let transmission = (1.0 - material.metallic) * material.transmission;
+    reflectance *= 1.0 - transmission;
+    if transmission <= 0.0 {
+        return (reflectance, Color::zeros(), None);
+    }
+
+    let entering = wo.dot(&normal) > 0.0;
+    let facing_normal = if entering { normal } else { -normal };
+    let eta = if entering {
+        1.0 / material.ior
+    } else {
+        material.ior
+    };
+
+    let fresnel = dielectric_fresnel(cos, eta);
+    let glass_reflectance = Color::repeat(fresnel * transmission);
+    let Some(refracted) = refract(wo, facing_normal, eta) else {
+        return (
+            reflectance + Color::repeat(transmission),
+            Color::zeros(),
+            None,
+        );
+    };
+
+    let transmittance = material.base_color / 255.0 * (1.0 - fresnel) * transmission;
+    (
+        reflectance + glass_reflectance,
+        transmittance,
+        Some(refracted),
+    )
+}
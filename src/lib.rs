@@ -1,32 +1,126 @@
+pub mod error;
+pub use error::Error;
+
 /// Dependencies and constants for modules
 pub mod config {
     /// Configurations for `rays.rs`
     pub mod rays {
-        pub use crate::objects::Intersection;
+        pub use crate::objects::{Intersection, Medium};
         pub use crate::raytracer::Scene;
-        pub use crate::type_aliases::{Color, Direction, Normal, Point};
+        pub use crate::samplers::Samples;
+        pub use crate::type_aliases::{Color, Direction, Directions, Normal, Point};
         pub use nalgebra::Vector3;
         pub use rand::Rng;
+        pub use std::sync::Arc;
 
-        pub const MAX_DEPTH: u8 = 50;
+        pub const DEFAULT_MAX_DEPTH: u32 = 50;
+
+        /// Depth from which paths are randomly terminated based on how much light they carry
+        pub const ROULETTE_DEPTH: u32 = 3;
+    }
+
+    /// Configurations for the integrators
+    pub mod integrators {
+        /// How far away objects still occlude a surface in the ambient occlusion view
+        pub const AO_DISTANCE: f64 = 2.0;
+
+        /// Distance at which the depth view fades to black
+        pub const DEPTH_VIEW_DISTANCE: f64 = 30.0;
+
+        /// Photons shot from the lights before every render of the photon mapper
+        pub const PHOTON_COUNT: usize = 200_000;
+
+        /// Distance around a surface point that photons are gathered from
+        pub const PHOTON_RADIUS: f64 = 0.1;
+
+        /// Photon maps shot by the progressive photon mapper
+        pub const PHOTON_PASSES: usize = 16;
     }
 
     /// Configurations for `camera.rs`
     pub mod camera {
+        pub use super::rays::DEFAULT_MAX_DEPTH;
         pub use crate::color::RGB;
-        pub use crate::raytracer::{Ray, Scene};
+        pub use crate::integrators::{Integrator, PathTracer};
+        pub use crate::raytracer::{
+            scene_to_text, split_into_tiles, Progress, ProgressCallback, Ray, Scene, Tile,
+            TileOrder,
+        };
+        pub use crate::samplers::{PixelSample, Sampler, Samples, Sobol};
         pub use crate::type_aliases::{Pixels, Point, Resolution};
+        pub use crate::Error;
         pub use nalgebra::Vector3;
         pub use rand::Rng;
         pub use rayon::prelude::*;
-        pub use std::io::Write;
-        pub use std::sync::Arc;
+        pub use std::io::{self, Write};
+        pub use std::net::ToSocketAddrs;
+        pub use std::path::{Path, PathBuf};
+        pub use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        pub use std::sync::{Arc, Mutex};
+        pub use std::time::{Duration, Instant};
 
         pub const DEFAULT_CAMERA_POSITION: Point = Point::new(1.0, 0.5, 0.0);
         pub const DEFAULT_SAMPLE_SIZE: u16 = 1000;
         pub const DEFAULT_FOCAL_LENGTH: f64 = 1.0;
         pub const DEFAULT_SENSOR_WIDTH: f64 = 1.0;
         pub const DEFAULT_RESOLUTION: Resolution = (800, 600);
+        pub const DEFAULT_EXPOSURE: f64 = 0.0;
+        pub const DEFAULT_TILE_SIZE: u32 = 16;
+
+        /// Time between the checkpoints of a render
+        pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+        /// Samples per pixel between checkpoints, for renders that would otherwise take all their
+        /// samples at once
+        pub const CHECKPOINT_PASS_SIZE: u64 = 8;
+
+        /// Samples per pixel of every pass of a progressive render
+        pub const DEFAULT_PASS_SIZE: u32 = 4;
+
+        /// With adaptive sampling, noisy pixels may take this many times the sample size
+        pub const ADAPTIVE_MAX_FACTOR: u16 = 4;
+
+        /// Samples every pixel takes before adaptive sampling judges its noise, at least one
+        /// `ADAPTIVE_MIN_DIVISOR`th of the sample size
+        pub const ADAPTIVE_MIN_SAMPLES: u32 = 16;
+        pub const ADAPTIVE_MIN_DIVISOR: u32 = 4;
+
+        /// Samples a noisy pixel takes between checks of its noise
+        pub const ADAPTIVE_BATCH: u64 = 16;
+
+        /// Brightness below which noise counts as absolute instead of relative, so dark pixels
+        /// are not sampled forever
+        pub const ADAPTIVE_MIN_LUMINANCE: f64 = 1.0;
+    }
+
+    /// Configurations for the textures
+    pub mod textures {
+        /// Luminance in nits, or candela per square metre, of a light that shows as white at an
+        /// exposure of 0
+        pub const REFERENCE_WHITE_NITS: f64 = 100.0;
+    }
+
+    /// Configurations for `denoise.rs`
+    pub mod denoise {
+        pub const DEFAULT_DENOISE_PASSES: u32 = 3;
+        pub const DEFAULT_COLOR_SIGMA: f64 = 3.0;
+        pub const DEFAULT_NORMAL_SIGMA: f64 = 0.3;
+        pub const DEFAULT_DEPTH_SIGMA: f64 = 0.1;
+        pub const DEFAULT_ALBEDO_SIGMA: f64 = 0.1;
+
+        /// Depth below which differences in depth count as absolute instead of relative, for
+        /// pixels that hit nothing
+        pub const MIN_DENOISE_DEPTH: f64 = 0.1;
+
+        /// Noise every pixel is allowed, so pixels without any noise still blend with the ones
+        /// that look the same
+        pub const MIN_DENOISE_NOISE: f64 = 1e-3;
+
+        /// Variance of the brightness of pixels too noisy to measure their noise
+        pub const MAX_DENOISE_VARIANCE: f64 = 1e6;
+
+        /// Smallest sigma the denoiser uses, smaller ones would divide by 0
+        pub const MIN_DENOISE_SIGMA: f64 = 1e-6;
     }
 }
 
@@ -50,6 +144,7 @@ pub mod type_aliases {
         fn down() -> Normal;
 
         fn near_zero(&self) -> bool;
+        fn to_world(&self, local: &Vector3<f64>) -> Direction;
     }
 
     impl Directions for Vector3<f64> {
@@ -66,6 +161,18 @@ pub mod type_aliases {
             let s = 1e-8;
             self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
         }
+
+        /// Transform `local` from the frame where `z` points along `self` to world space
+        fn to_world(&self, local: &Vector3<f64>) -> Direction {
+            let tangent_a = if self.x.abs() > 0.9 {
+                Vector3::new(0.0, 1.0, 0.0)
+            } else {
+                Vector3::new(1.0, 0.0, 0.0)
+            };
+            let tangent_v = self.cross(&tangent_a).normalize();
+            let tangent_u = tangent_v.cross(self);
+            (tangent_u * local.x + tangent_v * local.y + self * local.z).normalize()
+        }
     }
 
     /// `Color` represents the RGB values of a color. It is later calculated down to the u8 type
@@ -77,8 +184,90 @@ pub mod type_aliases {
     pub type Resolution = (u32, u32);
 }
 
+/// Reproducible random numbers, so renders with the same seed match exactly
+pub mod random {
+    use rand::RngCore;
+
+    /// ### SplitMix64
+    ///
+    /// Small generator with a fixed algorithm, so a seed gives the same numbers with every
+    /// release of `rand`. See [SplitMix64](https://prng.di.unimi.it/splitmix64.c).
+    #[derive(Debug, Clone)]
+    pub struct SplitMix64 {
+        state: u64,
+    }
+
+    impl SplitMix64 {
+        pub fn new(seed: u64) -> Self {
+            Self { state: seed }
+        }
+    }
+
+    impl RngCore for SplitMix64 {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let value = mix(self.state);
+            self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            value
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    /// Generator for a single stream of random numbers, such as one sample of one pixel.
+    ///
+    /// The same `seed`, `stream` and `index` always give the same numbers, no matter which
+    /// thread asks for them.
+    pub fn seeded_rng(seed: u64, stream: u64, index: u64) -> SplitMix64 {
+        SplitMix64::new(mix(mix(seed ^ mix(stream)) ^ index))
+    }
+
+    /// Hash of all `values`, for decorrelating streams that are not generators themselves
+    pub(crate) fn hash(values: &[u64]) -> u64 {
+        values.iter().fold(0, |hash, value| mix(hash ^ value))
+    }
+
+    /// Number in the 0.0-1.0 range from the top bits of `bits`
+    pub(crate) fn unit_float(bits: u64) -> f64 {
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// [SplitMix64](https://prng.di.unimi.it/splitmix64.c) finalizer
+    fn mix(value: u64) -> u64 {
+        let mut value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn split_mix_gives_the_numbers_of_the_reference() {
+            let mut rng = SplitMix64::new(0);
+            assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+            assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+            assert_eq!(rng.next_u64(), 0x06C4_5D18_8009_454F);
+        }
+    }
+}
+
 pub mod color {
-    use crate::raytracer::Scene;
     use nalgebra::Vector3;
 
     /// `Color` is a utility-trait for the `Vector3` type.
@@ -93,6 +282,7 @@ pub mod color {
         fn black() -> Self;
         fn white() -> Self;
         fn random() -> Self;
+        fn random_from<R: rand::Rng>(rng: &mut R) -> Self;
 
         fn red() -> Self;
         fn green() -> Self;
@@ -148,8 +338,9 @@ pub mod color {
             Self::new(255., 255., 255.)
         }
         fn random() -> Self {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
+            Self::random_from(&mut rand::thread_rng())
+        }
+        fn random_from<R: rand::Rng>(rng: &mut R) -> Self {
             Self::new(
                 rng.gen_range(0.0..255.),
                 rng.gen_range(0.0..255.),
@@ -271,77 +462,25 @@ pub mod color {
             Vector3::new(r, g, b)
         }
     }
-
-    impl crate::config::camera::Ray {
-        /// Calculates the average color of the pixel using following calculation:
-        ///
-        /// 1. Assign the light source that the ray hit as the  
-        pub fn average_color(&mut self, scene: &Scene) -> crate::type_aliases::Color {
-            if self.collisions.len() == 1 {
-                return self.collisions[0];
-            }
-
-            let mut total = if self.hit_light_source {
-                self.collisions.pop().unwrap()
-            } else {
-                scene.background() / 5.0
-            };
-
-            if total.x <= 5.0 {
-                total.x = 5.0;
-            }
-
-            if total.y <= 5.0 {
-                total.y = 5.0;
-            }
-
-            if total.z <= 5.0 {
-                total.z = 5.0;
-            }
-
-            self.collisions.iter().rev().for_each(|color| {
-                let r = if color.x <= 10.0 {
-                    5.0 / 255.
-                } else {
-                    color.x / 255.
-                };
-
-                let g = if color.y <= 10.0 {
-                    5.0 / 255.
-                } else {
-                    color.y / 255.
-                };
-
-                let b = if color.z <= 10.0 {
-                    5.0 / 255.
-                } else {
-                    color.z / 255.
-                };
-
-                total.x *= r;
-                total.y *= g;
-                total.z *= b;
-            });
-
-            total
-        }
-    }
 }
 
+#[cfg(feature = "gui")]
 pub mod gui {
     pub use gdk_pixbuf::Pixbuf;
     pub use glib::clone;
     pub use glib::signal::Inhibit;
     pub use gtk::{prelude::*, Image};
     pub use gtk::{
-        Box as GtkBox, Button, ComboBoxText, CssProvider, Entry, FlowBox, Orientation, Scale,
-        Separator, Window, WindowType,
+        Box as GtkBox, Button, ComboBoxText, CssProvider, Entry, FlowBox, Orientation, ProgressBar,
+        Scale, Separator, Window, WindowType,
     };
     pub use nalgebra::Vector3;
     pub use std::cell::RefCell;
     pub use std::rc::Rc;
     pub use std::sync::Arc;
 
+    use crate::textures::Principled;
+
     pub struct AppState {
         pub spheres: Vec<SphereConfig>,
         pub cylinders: Vec<CylinderConfig>,
@@ -358,6 +497,7 @@ pub mod gui {
         pub radius_entry: Rc<RefCell<Entry>>,
         pub material_selector: Rc<RefCell<ComboBoxText>>,
         pub color_button: Rc<RefCell<gtk::ColorButton>>,
+        pub principled: Rc<RefCell<Principled>>,
     }
     #[derive(Clone)]
     pub struct CylinderConfig {
@@ -369,6 +509,7 @@ pub mod gui {
         pub material_selector: Rc<RefCell<ComboBoxText>>,
         pub height_entry: Rc<RefCell<Entry>>,
         pub color_button: Rc<RefCell<gtk::ColorButton>>,
+        pub principled: Rc<RefCell<Principled>>,
     }
 
     pub struct CubeConfig {
@@ -379,6 +520,7 @@ pub mod gui {
         pub radius_entry: Rc<RefCell<Entry>>,
         pub material_selector: Rc<RefCell<ComboBoxText>>,
         pub color_button: Rc<RefCell<gtk::ColorButton>>,
+        pub principled: Rc<RefCell<Principled>>,
     }
 
     pub struct FlatPlaneConfig {
@@ -389,6 +531,39 @@ pub mod gui {
         pub radius_entry: Rc<RefCell<Entry>>,
         pub material_selector: Rc<RefCell<ComboBoxText>>,
         pub color_button: Rc<RefCell<gtk::ColorButton>>,
+        pub principled: Rc<RefCell<Principled>>,
+    }
+
+    /// Style sheet and icon of the interface, built into the binary so it runs from any directory
+    const STYLE: &[u8] = include_bytes!("gui/style.css");
+    const ICON: &[u8] = include_bytes!("gui/RT.png");
+
+    /// CSS provider with the style of the interface. The interface works without it, so a style
+    /// sheet that does not load is only reported.
+    pub fn style_provider() -> CssProvider {
+        let provider = CssProvider::new();
+        if let Err(error) = provider.load_from_data(STYLE) {
+            eprintln!("Could not load the style sheet: {error}");
+        }
+        provider
+    }
+
+    /// Icon of the application, `size` pixels wide and high
+    pub fn app_icon(size: i32) -> Option<Pixbuf> {
+        use gdk_pixbuf::PixbufLoaderExt;
+
+        let loader = gdk_pixbuf::PixbufLoader::new();
+        let icon = loader
+            .write(ICON)
+            .and_then(|()| loader.close())
+            .map_err(|error| error.to_string())
+            .and_then(|()| loader.get_pixbuf().ok_or_else(|| "Empty image".to_string()))
+            .and_then(|icon| {
+                icon.scale_simple(size, size, gdk_pixbuf::InterpType::Bilinear)
+                    .ok_or_else(|| "Could not scale it".to_string())
+            });
+        icon.map_err(|error| eprintln!("Could not load the icon: {error}"))
+            .ok()
     }
 
     pub mod interface;
@@ -417,17 +592,343 @@ pub mod gui {
 
         pub mod about;
         pub use about::*;
+
+        pub mod material_editor;
+        pub use material_editor::*;
     }
 }
 
 pub mod raytracer {
 
+    pub mod aov;
+    pub use aov::*;
     pub mod camera;
     pub use camera::*;
+    mod checkpoint;
+    pub mod denoise;
+    pub use denoise::*;
+    pub mod distributed;
+    pub use distributed::*;
+    pub mod image;
+    pub use image::*;
     pub mod ray;
     pub use ray::*;
+    pub mod render;
+    pub use render::*;
     pub mod scene;
     pub use scene::*;
+    pub mod scene_file;
+    pub use scene_file::*;
+    pub mod statistics;
+    pub use statistics::*;
+    pub mod tiles;
+    pub use tiles::*;
+    pub mod validation;
+    pub use validation::*;
+}
+
+pub mod integrators {
+    use crate::config::integrators::{DEPTH_VIEW_DISTANCE, PHOTON_PASSES};
+    use crate::raytracer::{Ray, Scene};
+    use crate::samplers::Samples;
+    use crate::type_aliases::Color;
+    use std::fmt::Debug;
+    use std::sync::Arc;
+
+    pub mod path_tracer;
+    pub use path_tracer::*;
+
+    pub mod whitted;
+    pub use whitted::*;
+
+    pub mod bdpt;
+    pub use bdpt::*;
+
+    pub mod photon_mapping;
+    pub use photon_mapping::*;
+
+    pub mod ambient_occlusion;
+    pub use ambient_occlusion::*;
+
+    pub mod debug;
+    pub use debug::*;
+
+    /// ### Integrator
+    ///
+    /// Computes how much light arrives along a camera ray. Every render picks one with
+    /// `CameraBuilder::integrator`, the `PathTracer` is used by default.
+    pub trait Integrator: Send + Sync + Debug {
+        /// Called once before every render, for work that depends on the whole scene. Random
+        /// numbers should come from `seed`, so renders with the same seed match.
+        fn prepare(&self, _scene: &Scene, _seed: u64) {}
+
+        /// Light arriving at the origin of `ray`, from the direction it points in
+        fn radiance(&self, ray: &mut Ray, scene: &Scene, rng: &mut Samples) -> Color;
+
+        /// Name of the integrator in `INTEGRATORS`
+        fn name(&self) -> &'static str;
+
+        /// Numbers that set the integrator up, which `with_settings` builds it back from
+        fn settings(&self) -> Vec<f64> {
+            Vec::new()
+        }
+    }
+
+    /// Names accepted by `from_name`
+    pub const INTEGRATORS: [&str; 10] = [
+        "path",
+        "bdpt",
+        "photon",
+        "ppm",
+        "whitted",
+        "ao",
+        "normals",
+        "depth",
+        "uv",
+        "object-id",
+    ];
+
+    /// Integrator with its default settings, by the name used in the GUI and on the command line
+    pub fn from_name(name: &str) -> Option<Arc<dyn Integrator>> {
+        let integrator: Arc<dyn Integrator> = match name {
+            "path" => Arc::new(PathTracer),
+            "bdpt" => Arc::new(BidirectionalPathTracer),
+            "photon" => Arc::new(PhotonMapper::default()),
+            "ppm" => Arc::new(PhotonMapper::default().progressive(PHOTON_PASSES)),
+            "whitted" => Arc::new(Whitted),
+            "ao" => Arc::new(AmbientOcclusion::default()),
+            "normals" => Arc::new(DebugView::Normals),
+            "depth" => Arc::new(DebugView::Depth {
+                max_distance: DEPTH_VIEW_DISTANCE,
+            }),
+            "uv" => Arc::new(DebugView::Uv),
+            "object-id" => Arc::new(DebugView::ObjectId),
+            _ => return None,
+        };
+        Some(integrator)
+    }
+
+    /// Integrator by its name and the numbers of `Integrator::settings`, like in scene files.
+    /// Without any numbers, this is the same as `from_name`.
+    pub fn with_settings(name: &str, settings: &[f64]) -> Option<Arc<dyn Integrator>> {
+        let integrator: Arc<dyn Integrator> = match (name, settings) {
+            (name, []) => return from_name(name),
+            ("photon", &[photon_count, radius]) => {
+                Arc::new(PhotonMapper::new(photon_count as usize, radius))
+            }
+            ("ppm", &[photon_count, radius, passes]) => Arc::new(
+                PhotonMapper::new(photon_count as usize, radius).progressive(passes as usize),
+            ),
+            ("ao", &[distance]) => Arc::new(AmbientOcclusion::new(distance)),
+            ("depth", &[max_distance]) => Arc::new(DebugView::Depth { max_distance }),
+            _ => return None,
+        };
+        Some(integrator)
+    }
+}
+
+pub mod samplers {
+    use crate::random::hash;
+    use rand::RngCore;
+    use std::fmt::Debug;
+    use std::sync::Arc;
+
+    pub mod independent;
+    pub use independent::*;
+
+    pub mod stratified;
+    pub use stratified::*;
+
+    pub mod halton;
+    pub use halton::*;
+
+    pub mod sobol;
+    pub use sobol::*;
+
+    pub mod blue_noise;
+    pub use blue_noise::*;
+
+    /// ### Sampler
+    ///
+    /// Decides the numbers every sample of a pixel draws, one dimension at a time: the first two
+    /// place the sample inside the pixel, the rest go to the integrator. Samplers that spread the
+    /// samples of a pixel evenly over every dimension converge with fewer samples than independent
+    /// random numbers. `CameraBuilder::sampler` picks one, `Sobol` is used by default.
+    pub trait Sampler: Send + Sync + Debug {
+        /// Number in the 0.0-1.0 range for `dimension` of `sample`
+        fn get(&self, sample: &PixelSample, dimension: u32) -> f64;
+
+        /// Name of the sampler in `SAMPLERS`
+        fn name(&self) -> &'static str;
+    }
+
+    /// One sample of one pixel
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct PixelSample {
+        pub seed: u64,
+        pub pixel: (u32, u32),
+        /// Which sample of the pixel this is
+        pub index: u64,
+        /// Amount of samples the pixel gets
+        pub count: u64,
+    }
+
+    impl PixelSample {
+        /// Hash of the seed, the pixel and `values`, to decorrelate pixels and dimensions
+        pub(crate) fn hash(&self, values: &[u64]) -> u64 {
+            let (x, y) = self.pixel;
+            values.iter().fold(
+                hash(&[self.seed, (x as u64) << 32 | y as u64]),
+                |key, value| hash(&[key, *value]),
+            )
+        }
+    }
+
+    /// ### Samples
+    ///
+    /// The numbers of a single sample, handed out one dimension at a time. Works as any other
+    /// `rand::Rng`, so everything that takes one draws from the sampler.
+    pub struct Samples<'a> {
+        sampler: &'a dyn Sampler,
+        sample: PixelSample,
+        dimension: u32,
+    }
+
+    impl<'a> Samples<'a> {
+        pub fn new(sampler: &'a dyn Sampler, sample: PixelSample) -> Self {
+            Self {
+                sampler,
+                sample,
+                dimension: 0,
+            }
+        }
+
+        fn next(&mut self) -> f64 {
+            let value = self.sampler.get(&self.sample, self.dimension);
+            self.dimension += 1;
+            value.clamp(0.0, 1.0 - f64::EPSILON)
+        }
+    }
+
+    impl RngCore for Samples<'_> {
+        fn next_u32(&mut self) -> u32 {
+            (self.next() * (1u64 << 32) as f64) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            // Floats are made from the top bits, which keeps the value of the sampler
+            (self.next() * 2f64.powi(64)) as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    /// Names accepted by `from_name`
+    pub const SAMPLERS: [&str; 5] = ["sobol", "halton", "stratified", "blue-noise", "independent"];
+
+    /// Sampler by the name used in the GUI and on the command line
+    pub fn from_name(name: &str) -> Option<Arc<dyn Sampler>> {
+        let sampler: Arc<dyn Sampler> = match name {
+            "sobol" => Arc::new(Sobol),
+            "halton" => Arc::new(Halton),
+            "stratified" => Arc::new(Stratified),
+            "blue-noise" => Arc::new(BlueNoise),
+            "independent" => Arc::new(Independent),
+            _ => return None,
+        };
+        Some(sampler)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn sample(index: u64, count: u64) -> PixelSample {
+            PixelSample {
+                seed: 3,
+                pixel: (5, 9),
+                index,
+                count,
+            }
+        }
+
+        /// Stratum of `count` equal strata every sample of a pixel with `count` samples lands in
+        fn strata(sampler: &dyn Sampler, dimension: u32, count: u64) -> Vec<u64> {
+            let mut strata: Vec<u64> = (0..count)
+                .map(|index| (sampler.get(&sample(index, count), dimension) * count as f64) as u64)
+                .collect();
+            strata.sort();
+            strata
+        }
+
+        #[test]
+        fn every_sampler_stays_in_range() {
+            for name in SAMPLERS {
+                let sampler = from_name(name).unwrap();
+                for count in [1, 7, 16, 100] {
+                    for index in 0..count * 2 {
+                        for dimension in 0..300 {
+                            let value = sampler.get(&sample(index, count), dimension);
+                            assert!((0.0..1.0).contains(&value), "{name} gave {value}");
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn stratified_and_sobol_fill_every_stratum_once() {
+            let every_stratum: Vec<u64> = (0..16).collect();
+            for dimension in 0..8 {
+                assert_eq!(strata(&Stratified, dimension, 16), every_stratum);
+                assert_eq!(strata(&Sobol, dimension, 16), every_stratum);
+            }
+        }
+
+        #[test]
+        fn stratified_fills_every_stratum_for_any_count() {
+            let every_stratum: Vec<u64> = (0..13).collect();
+            for dimension in 0..8 {
+                assert_eq!(strata(&Stratified, dimension, 13), every_stratum);
+            }
+        }
+
+        #[test]
+        fn halton_fills_every_stratum_of_its_first_base() {
+            let every_stratum: Vec<u64> = (0..16).collect();
+            assert_eq!(strata(&Halton, 0, 16), every_stratum);
+            let every_stratum: Vec<u64> = (0..9).collect();
+            assert_eq!(strata(&Halton, 1, 9), every_stratum);
+        }
+
+        #[test]
+        fn pixels_and_seeds_get_their_own_numbers() {
+            for name in SAMPLERS {
+                let sampler = from_name(name).unwrap();
+                let other_pixel = PixelSample {
+                    pixel: (6, 9),
+                    ..sample(0, 16)
+                };
+                let other_seed = PixelSample {
+                    seed: 4,
+                    ..sample(0, 16)
+                };
+                let value = sampler.get(&sample(0, 16), 0);
+                assert_ne!(value, sampler.get(&other_pixel, 0), "{name}");
+                assert_ne!(value, sampler.get(&other_seed, 0), "{name}");
+            }
+        }
+    }
 }
 
 pub mod objects {
@@ -441,9 +942,22 @@ pub mod objects {
     pub use flat_plane::*;
 
     pub mod sphere;
-    use crate::raytracer::Ray;
+    pub mod volume;
+    pub use volume::*;
+
+    pub mod voxel_grid;
+    pub use voxel_grid::*;
+
+    pub mod quad;
+    pub use quad::*;
+
+    pub mod triangle;
+    pub use triangle::*;
+
+    use crate::raytracer::{Checks, Ray};
     use crate::textures::Texture;
     use crate::type_aliases::{Normal, Point};
+    use rand::RngCore;
     pub use sphere::*;
 
     /// [Discriminant equation](https://en.wikipedia.org/wiki/Discriminant)
@@ -461,6 +975,38 @@ pub mod objects {
     pub trait Object: Send + Sync {
         fn intersection(&self, ray: &Ray) -> Option<Intersection>;
         fn texture(&self) -> Texture;
+
+        /// Name of the kind of object, the same as its keyword in scene files
+        fn name(&self) -> &'static str;
+
+        /// Whether `point` is inside the object. Flat objects have no inside.
+        fn contains(&self, _point: &Point) -> bool {
+            false
+        }
+
+        /// Surface area, used to sample emissive objects as lights
+        fn area(&self) -> f64 {
+            0.0
+        }
+
+        /// Uniformly pick a point on the surface, with the normal at that point.
+        /// The density of the sample is `1.0 / area()`.
+        fn sample_surface(&self, _rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+            None
+        }
+
+        /// Texture coordinates of a point on the surface, both in the 0.0-1.0 range
+        fn uv(&self, _point: &Point) -> (f64, f64) {
+            (0.0, 0.0)
+        }
+
+        /// The object as a line of a scene file, `None` when the format has no way to write it
+        fn describe(&self) -> Option<String> {
+            None
+        }
+
+        /// Report the fields that make the object impossible to render, see `Scene::validate`
+        fn validate(&self, _checks: &mut Checks) {}
     }
 
     pub type Objects = Vec<Arc<dyn Object>>;
@@ -489,10 +1035,57 @@ pub mod objects {
 pub mod textures {
     use crate::type_aliases::Color;
 
+    pub mod emission;
+    pub use emission::*;
+
+    pub mod principled;
+    pub use principled::*;
+
+    pub mod bsdf;
+    pub use bsdf::*;
+
     #[derive(Debug, Clone, Copy, PartialEq)]
     pub enum Texture {
-        Light(Color),
+        Light(Emission),
         Diffusive(Color),
         Reflective,
+        Principled(Principled),
+    }
+}
+
+/// Fixtures shared by the tests of every module
+#[cfg(test)]
+pub(crate) mod test_support {
+    use crate::raytracer::CameraBuilder;
+    use std::path::PathBuf;
+
+    /// File in the temporary directory that is removed again when dropped
+    pub struct TempFile(pub PathBuf);
+
+    impl TempFile {
+        /// `name` goes after the id of the process, so tests running at the same time don't
+        /// share files
+        pub fn new(name: &str) -> Self {
+            let name = format!("rt-{}-{name}", std::process::id());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            std::fs::remove_file(&self.0).ok();
+        }
+    }
+
+    /// Camera that takes `samples` per pixel of the default scene, small enough to render in a
+    /// test
+    pub fn small_camera(samples: u16) -> CameraBuilder {
+        let mut builder = CameraBuilder::new();
+        builder
+            .default_scene_view()
+            .resolution(24, 16)
+            .sample_size(samples)
+            .seed(7);
+        builder
     }
 }
@@ -1,33 +1,214 @@
+mod cli;
+
+use cli::{parse_arguments, usage, Command, Options};
+#[cfg(feature = "gui")]
 use rt::gui::launch_gui;
-use rt::raytracer::{CameraBuilder, Scene};
-use rt::type_aliases::Point;
+use rt::raytracer::{
+    load_scene, serve_worker, Aov, Camera, CameraBuilder, Image, ImageFormat, PixelFormat,
+    Progressive, RenderEnd, Scene, WorkerEvent,
+};
+use rt::Error;
 use std::env;
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 const OUTPUT_PATH: &str = "output.ppm";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.contains(&"no-gui".to_string()) {
-        let mut camera = CameraBuilder::new()
-            .sample_size(100)
-            .position_by_coordinates(Point::new(-6.0, 6.0, 15.0))
-            .look_at(Point::new(0.0, 0.0, 0.0))
-            .focal_length(2.0)
-            .resolution(800, 600)
-            .build();
-
-        let scene = Arc::new(Scene::init(0.5));
-
-        let start = Instant::now();
-
-        // Perform ray tracing
-        camera.send_rays(scene.clone());
-        camera.write_to_ppm(OUTPUT_PATH);
-
-        let duration = start.elapsed();
-        println!("Time taken for rendering: {:?}", duration);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = parse_arguments(&args).unwrap_or_else(|message| {
+        eprintln!("{message}");
+        std::process::exit(2);
+    });
+
+    let result = match command {
+        Command::Help => {
+            print!("{}", usage());
+            Ok(())
+        }
+        Command::Worker(address) => work(&address),
+        Command::Render(options) => render(&options),
+        #[cfg(feature = "gui")]
+        Command::Gui => launch_gui().map_err(|error| error.to_string()),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{message}");
+        std::process::exit(1);
+    }
+}
+
+/// Render the jobs of coordinators until the worker is stopped
+fn work(address: &str) -> Result<(), String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|error| format!("Could not listen on {address}: {error}"))?;
+    println!("Waiting for jobs on {address}");
+    serve_worker(listener, |event| match event {
+        WorkerEvent::Started(coordinator) => println!("Rendering for {coordinator}"),
+        WorkerEvent::Finished { coordinator, tiles } => {
+            println!("Rendered {tiles} tiles for {coordinator}")
+        }
+        WorkerEvent::Failed { coordinator, error } => {
+            eprintln!("Job of {coordinator} failed: {error}")
+        }
+    })
+    .map_err(|error| format!("Worker stopped: {error}"))
+}
+
+/// Scene and camera rendered without a scene file
+fn default_scene() -> (Scene, CameraBuilder) {
+    let mut camera = CameraBuilder::new();
+    camera
+        .sample_size(100)
+        .default_scene_view()
+        .resolution(800, 600);
+    (Scene::init(0.5), camera)
+}
+
+fn render(options: &Options) -> Result<(), String> {
+    if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|error| format!("Could not start {threads} threads: {error}"))?;
+    }
+
+    let (scene, mut builder) = match &options.scene {
+        // Mistakes in the scene file are reported with the line, and the file goes in front
+        Some(path) => load_scene(path).map_err(|error| match error {
+            Error::Parse { .. } => format!("{path}: {error}"),
+            _ => error.to_string(),
+        })?,
+        None => default_scene(),
+    };
+    options.apply(&mut builder);
+
+    // Print the progress whenever another percent is done
+    let last_percent = AtomicU32::new(0);
+    builder.on_progress(move |progress| {
+        let percent = (progress.done * 100.0) as u32;
+        if last_percent.fetch_max(percent, Ordering::Relaxed) < percent {
+            let eta = progress.eta.unwrap_or_default().as_secs();
+            print!(
+                "\rRendering: {percent:3}%, {}:{:02} left ",
+                eta / 60,
+                eta % 60
+            );
+            std::io::stdout().flush().ok();
+        }
+    });
+    let mut camera = builder.build();
+
+    let issues = scene.validate(&camera);
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        return Err(format!(
+            "Can not render the scene:\n  {}",
+            issues.join("\n  ")
+        ));
+    }
+
+    // Keep the samples of an earlier render, and only take the ones it was missing
+    if let Some(path) = &options.resume {
+        camera
+            .resume(path)
+            .map_err(|error| format!("Could not resume: {error}"))?;
+    }
+
+    let output = options.output.as_deref().unwrap_or(OUTPUT_PATH);
+    let format = options
+        .format
+        .or_else(|| ImageFormat::from_path(output))
+        .unwrap_or_default();
+
+    // Depth and normals do not fit in 8 bits, so they would be clamped
+    let unclamped = [Aov::Depth, Aov::Normal];
+    if let Some(aov) = options.aovs.iter().find(|aov| unclamped.contains(aov)) {
+        if format != ImageFormat::Pfm {
+            return Err(format!(
+                "The {} AOV needs the pfm format, use --format pfm or a .pfm output",
+                aov.name()
+            ));
+        }
+    }
+    let save = |camera: &Camera| {
+        Image::from_camera(camera, PixelFormat::Rgb)
+            .save(output, format)
+            .map_err(|error| format!("Could not save the image: {error}"))
+    };
+
+    let scene = Arc::new(scene);
+    let start = Instant::now();
+
+    // Perform ray tracing
+    if !options.workers.is_empty() {
+        if options.denoise || !options.aovs.is_empty() {
+            return Err("Distributed renders can not be denoised or have AOVs".to_string());
+        }
+        let rendered = camera.send_rays_distributed(scene, &options.workers);
+        for error in &camera.worker_errors {
+            if let Error::Worker { index, source } = error {
+                eprintln!("\nWorker {} failed: {source}", options.workers[*index]);
+            }
+        }
+        rendered.map_err(|error| format!("\nDistributed render failed: {error}"))?;
+    } else if options.time_limit.is_some() || options.progressive {
+        let mut progressive = Progressive::default();
+        if let Some(time_limit) = options.time_limit {
+            progressive = progressive.time_budget(time_limit);
+        }
+
+        // Write the image after every pass, so it can be looked at while it renders
+        let mut saved = Ok(());
+        let end = camera.send_rays_progressive(scene, &progressive, |camera, pass| {
+            saved = save(camera);
+            println!(
+                "\rPass {}: {:.1} samples per pixel after {:.1?}",
+                pass.number,
+                pass.samples as f64 / camera.pixels.len() as f64,
+                pass.elapsed
+            );
+        });
+        saved?;
+        if end == RenderEnd::OutOfTime {
+            println!("Stopped at the time limit");
+        }
     } else {
-        launch_gui();
+        camera.send_rays(scene);
+    }
+    save(&camera)?;
+    if let Some(error) = &camera.checkpoint_error {
+        eprintln!("\nCould not save the checkpoint: {error}");
+    }
+
+    for &aov in &options.aovs {
+        let path = aov_path(output, aov);
+        let image = camera
+            .aov_image(aov)
+            .expect("Renders with --aovs collect them");
+        image
+            .save(&path, format)
+            .map_err(|error| format!("Could not save the {} AOV: {error}", aov.name()))?;
     }
+
+    let duration = start.elapsed();
+    println!("\n{}", camera.stats);
+    println!("\nTime taken for rendering: {:?}", duration);
+    Ok(())
+}
+
+/// Where `aov` goes next to the image at `output`, such as `render.depth.pfm` for `render.pfm`
+fn aov_path(output: &str, aov: Aov) -> String {
+    let path = Path::new(output);
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}", aov.name(), extension.to_string_lossy()),
+        None => aov.name().to_string(),
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{name}"))
+        .to_string_lossy()
+        .into_owned()
 }
@@ -1,6 +1,7 @@
 use crate::objects::{Intersection, Object, Texture};
-use crate::raytracer::Ray;
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
 use crate::type_aliases::{Normal, Point};
+use rand::{Rng, RngCore};
 
 #[derive(Debug)]
 pub struct Cube {
@@ -24,7 +25,7 @@ impl Cube {
             .iter()
             .map(|v| v.abs())
             .enumerate()
-            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
             .map(|(index, _)| index)
             .unwrap_or(0);
 
@@ -96,4 +97,61 @@ impl Object for Cube {
     fn texture(&self) -> Texture {
         self.texture
     }
+
+    fn name(&self) -> &'static str {
+        "cube"
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        let half_size = self.size / 2.0;
+        (point - self.center).iter().all(|v| v.abs() < half_size)
+    }
+
+    fn area(&self) -> f64 {
+        6.0 * self.size * self.size
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let face = rng.gen_range(0..6);
+        let axis = face % 3;
+        let sign = if face < 3 { 1.0 } else { -1.0 };
+
+        let mut normal = Normal::default();
+        normal[axis] = sign;
+
+        let mut local_point = Point::new(
+            rng.gen::<f64>() - 0.5,
+            rng.gen::<f64>() - 0.5,
+            rng.gen::<f64>() - 0.5,
+        );
+        local_point[axis] = sign * 0.5;
+
+        Some((self.center + local_point * self.size, normal))
+    }
+
+    /// Every face is mapped over the whole 0.0-1.0 range
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let local = ((point - self.center) / self.size).map(|v| (v + 0.5).clamp(0.0, 1.0));
+        match self.normal(*point).iamax() {
+            0 => (local.z, local.y),
+            1 => (local.x, local.z),
+            _ => (local.x, local.y),
+        }
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "cube {} {} {}",
+            vector_to_text(&self.center),
+            self.size,
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        checks
+            .point("center", &self.center)
+            .positive("size", self.size)
+            .texture(&self.texture);
+    }
 }
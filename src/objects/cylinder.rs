@@ -1,7 +1,9 @@
 use super::Texture;
 use crate::objects::{discriminant, FlatPlane, Intersection, Object};
-use crate::raytracer::Ray;
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
 use crate::type_aliases::{Direction, Directions, Normal, Point};
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
 
 #[derive(Debug)]
 pub struct Cylinder {
@@ -50,7 +52,7 @@ impl Object for Cylinder {
     fn intersection(&self, ray: &Ray) -> Option<Intersection> {
         let bottom = self.bottom.center;
         let axis = Direction::up(); // Cylinder aligned along Y-axis
-        let mut valid_intersections = Vec::new();
+        let mut closest: Option<Intersection> = None;
 
         // Check intersection with cylindrical surface
         let vec_to_ray = ray.origin - bottom;
@@ -68,8 +70,9 @@ impl Object for Cylinder {
             let dist_1 = (-b - sqrt_discriminant) / (2.0 * a);
             let dist_2 = (-b + sqrt_discriminant) / (2.0 * a);
 
+            // Rays along the axis have `a` of 0, and no distance to the side
             for dist in [dist_1, dist_2] {
-                if dist <= 0.0 {
+                if dist.is_nan() || dist <= 0.0 {
                     continue;
                 }
 
@@ -84,31 +87,101 @@ impl Object for Cylinder {
                         1.0
                     };
 
-                    valid_intersections.push(Intersection::new(
+                    closest = Some(Intersection::new(
                         point * offset,
                         self.normal(point),
                         dist,
                         self.texture,
                     ));
+                    break; // dist_1 is never further away than dist_2
                 }
             }
         }
 
-        // Check intersections with both caps
-        if let Some(bottom_intersection) = self.bottom.intersection(ray) {
-            valid_intersections.push(bottom_intersection);
-        }
-        if let Some(top_intersection) = self.top.intersection(ray) {
-            valid_intersections.push(top_intersection);
+        // Check intersections with both caps, keeping the closest one
+        for cap in [&self.bottom, &self.top] {
+            if let Some(cap_intersection) = cap.intersection(ray) {
+                if closest
+                    .as_ref()
+                    .is_none_or(|closest| cap_intersection.distance < closest.distance)
+                {
+                    closest = Some(cap_intersection);
+                }
+            }
         }
 
-        // Find the closest valid intersection. If no intersection was found. Return None.
-        valid_intersections
-            .into_iter()
-            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+        closest
     }
 
     fn texture(&self) -> Texture {
         self.texture
     }
+
+    fn name(&self) -> &'static str {
+        "cylinder"
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        let local_point = point - self.center;
+        let radial = Point::new(local_point.x, 0.0, local_point.z);
+        (0.0..self.height).contains(&local_point.y) && radial.norm() < self.radius
+    }
+
+    fn area(&self) -> f64 {
+        2.0 * PI * self.radius * self.height + self.bottom.area() + self.top.area()
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let side = 2.0 * PI * self.radius * self.height;
+        let pick = rng.gen::<f64>() * self.area();
+
+        if pick < self.bottom.area() {
+            let (point, _) = self.bottom.sample_surface(rng)?;
+            return Some((point, Normal::down()));
+        }
+        if pick < self.bottom.area() + self.top.area() {
+            return self.top.sample_surface(rng);
+        }
+
+        let phi = 2.0 * PI * (pick - self.bottom.area() - self.top.area()) / side;
+        let normal = Normal::new(phi.cos(), 0.0, phi.sin());
+        let height = rng.gen::<f64>() * self.height;
+
+        Some((
+            self.center + normal * self.radius + Normal::up() * height,
+            normal,
+        ))
+    }
+
+    /// The side wraps around the `u` axis, the caps use the coordinates of their flat planes
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let local = point - self.center;
+        if local.y <= 1e-6 {
+            return self.bottom.uv(point);
+        }
+        if local.y >= self.height - 1e-6 {
+            return self.top.uv(point);
+        }
+
+        let u = 0.5 + local.z.atan2(local.x) / (2.0 * PI);
+        (u, (local.y / self.height).clamp(0.0, 1.0))
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "cylinder {} {} {} {}",
+            vector_to_text(&self.center),
+            self.radius,
+            self.height,
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        checks
+            .point("center", &self.center)
+            .positive("radius", self.radius)
+            .positive("height", self.height)
+            .texture(&self.texture);
+    }
 }
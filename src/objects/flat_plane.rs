@@ -1,6 +1,8 @@
 use crate::objects::{Intersection, Object};
-use crate::raytracer::Ray;
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
 use crate::type_aliases::{Directions, Normal, Point};
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
 
 use super::Texture;
 
@@ -57,4 +59,44 @@ impl Object for FlatPlane {
     fn texture(&self) -> Texture {
         self.texture
     }
+
+    fn name(&self) -> &'static str {
+        "plane"
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let radius = self.radius * rng.gen::<f64>().sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let offset = Point::new(radius * phi.cos(), 0.0, radius * phi.sin());
+
+        Some((self.center + offset, Normal::up()))
+    }
+
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let local = (point - self.center) / (2.0 * self.radius);
+        (
+            (local.x + 0.5).clamp(0.0, 1.0),
+            (local.z + 0.5).clamp(0.0, 1.0),
+        )
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "plane {} {} {}",
+            vector_to_text(&self.center),
+            self.radius,
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        checks
+            .point("center", &self.center)
+            .positive("radius", self.radius)
+            .texture(&self.texture);
+    }
 }
@@ -0,0 +1,116 @@
+use crate::objects::{Intersection, Object};
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
+use crate::type_aliases::{Direction, Normal, Point};
+use rand::{Rng, RngCore};
+
+use super::Texture;
+
+/// ### Quad
+///
+/// Parallelogram spanned by `edge_u` and `edge_v` from `corner`. Visible from both sides,
+/// which makes it a good fit for area lights.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    pub corner: Point,
+    pub edge_u: Direction,
+    pub edge_v: Direction,
+    pub texture: Texture,
+}
+
+impl Quad {
+    pub fn new(corner: Point, edge_u: Direction, edge_v: Direction, texture: Texture) -> Self {
+        Self {
+            corner,
+            edge_u,
+            edge_v,
+            texture,
+        }
+    }
+
+    fn normal(&self) -> Normal {
+        self.edge_u.cross(&self.edge_v).normalize()
+    }
+}
+
+impl Object for Quad {
+    fn intersection(&self, ray: &Ray) -> Option<Intersection> {
+        let span = self.edge_u.cross(&self.edge_v);
+        let denom = ray.direction.dot(&span);
+        if denom.abs() <= 1e-12 {
+            return None;
+        }
+
+        let dist = (self.corner - ray.origin).dot(&span) / denom;
+        if !(1e-6..ray.intersection_dist).contains(&dist) {
+            return None;
+        }
+
+        // Coordinates of the hit point along both edges
+        let hit_point = ray.origin + ray.direction * dist;
+        let (u, v) = self.uv(&hit_point);
+
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+
+        // Face the normal towards the ray
+        let normal = if denom > 0.0 {
+            -self.normal()
+        } else {
+            self.normal()
+        };
+
+        Some(Intersection::new(hit_point, normal, dist, self.texture()))
+    }
+
+    fn texture(&self) -> Texture {
+        self.texture
+    }
+
+    fn name(&self) -> &'static str {
+        "quad"
+    }
+
+    fn area(&self) -> f64 {
+        self.edge_u.cross(&self.edge_v).norm()
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let point = self.corner + self.edge_u * rng.gen::<f64>() + self.edge_v * rng.gen::<f64>();
+        Some((point, self.normal()))
+    }
+
+    /// Coordinates of the point along both edges
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let span = self.edge_u.cross(&self.edge_v);
+        let local_point = point - self.corner;
+        let scale = span / span.dot(&span);
+        (
+            scale.dot(&local_point.cross(&self.edge_v)),
+            scale.dot(&self.edge_u.cross(&local_point)),
+        )
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "quad {} {} {} {}",
+            vector_to_text(&self.corner),
+            vector_to_text(&self.edge_u),
+            vector_to_text(&self.edge_v),
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        checks
+            .point("corner", &self.corner)
+            .point("edge_u", &self.edge_u)
+            .point("edge_v", &self.edge_v)
+            .require(
+                self.edge_u.cross(&self.edge_v).norm() > 0.0,
+                "edges",
+                "must not be zero or parallel",
+            )
+            .texture(&self.texture);
+    }
+}
@@ -1,6 +1,8 @@
 use crate::objects::{Intersection, Object};
-use crate::raytracer::Ray;
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
 use crate::type_aliases::{Normal, Point};
+use rand::{Rng, RngCore};
+use std::f64::consts::PI;
 
 use super::Texture;
 
@@ -62,4 +64,48 @@ impl Object for Sphere {
     fn texture(&self) -> Texture {
         self.texture
     }
+
+    fn name(&self) -> &'static str {
+        "sphere"
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        (point - self.center).norm() < self.radius
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let z = 1.0 - 2.0 * rng.gen::<f64>();
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let normal = Normal::new(radius * phi.cos(), radius * phi.sin(), z);
+
+        Some((self.center + normal * self.radius, normal))
+    }
+
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let local = self.normal(*point);
+        let u = 0.5 + local.z.atan2(local.x) / (2.0 * PI);
+        let v = local.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, 1.0 - v)
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "sphere {} {} {}",
+            vector_to_text(&self.center),
+            self.radius,
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        checks
+            .point("center", &self.center)
+            .positive("radius", self.radius)
+            .texture(&self.texture);
+    }
 }
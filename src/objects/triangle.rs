@@ -0,0 +1,142 @@
+use crate::objects::{Intersection, Object};
+use crate::raytracer::{texture_to_text, vector_to_text, Checks, Ray};
+use crate::type_aliases::{Normal, Point};
+use rand::{Rng, RngCore};
+
+use super::Texture;
+
+/// ### Triangle
+///
+/// Single triangle between three points, visible from both sides.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub vertices: [Point; 3],
+    pub texture: Texture,
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point, texture: Texture) -> Self {
+        Self {
+            vertices: [a, b, c],
+            texture,
+        }
+    }
+
+    fn normal(&self) -> Normal {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(&(c - a)).normalize()
+    }
+}
+
+impl Object for Triangle {
+    /// [Möller–Trumbore intersection](https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm)
+    fn intersection(&self, ray: &Ray) -> Option<Intersection> {
+        let [a, b, c] = self.vertices;
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+
+        let p = ray.direction.cross(&edge_2);
+        let determinant = edge_1.dot(&p);
+        if determinant.abs() <= 1e-12 {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let t = ray.origin - a;
+        let u = t.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t.cross(&edge_1);
+        let v = ray.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let dist = edge_2.dot(&q) * inverse;
+        if !(1e-6..ray.intersection_dist).contains(&dist) {
+            return None;
+        }
+
+        // Face the normal towards the ray
+        let normal = if ray.direction.dot(&self.normal()) > 0.0 {
+            -self.normal()
+        } else {
+            self.normal()
+        };
+
+        Some(Intersection::new(
+            ray.origin + ray.direction * dist,
+            normal,
+            dist,
+            self.texture(),
+        ))
+    }
+
+    fn texture(&self) -> Texture {
+        self.texture
+    }
+
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn area(&self) -> f64 {
+        let [a, b, c] = self.vertices;
+        0.5 * (b - a).cross(&(c - a)).norm()
+    }
+
+    fn sample_surface(&self, rng: &mut dyn RngCore) -> Option<(Point, Normal)> {
+        let [a, b, c] = self.vertices;
+        let root = rng.gen::<f64>().sqrt();
+        let v = rng.gen::<f64>();
+        let point = a * (1.0 - root) + b * (root * (1.0 - v)) + c * (root * v);
+
+        Some((point, self.normal()))
+    }
+
+    /// Barycentric coordinates of the point, relative to the first vertex
+    fn uv(&self, point: &Point) -> (f64, f64) {
+        let [a, b, c] = self.vertices;
+        let (edge_1, edge_2, local_point) = (b - a, c - a, point - a);
+
+        let d11 = edge_1.dot(&edge_1);
+        let d12 = edge_1.dot(&edge_2);
+        let d22 = edge_2.dot(&edge_2);
+        let denominator = d11 * d22 - d12 * d12;
+        if denominator.abs() <= 1e-12 {
+            return (0.0, 0.0);
+        }
+
+        let (p1, p2) = (local_point.dot(&edge_1), local_point.dot(&edge_2));
+        (
+            (d22 * p1 - d12 * p2) / denominator,
+            (d11 * p2 - d12 * p1) / denominator,
+        )
+    }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!(
+            "triangle {} {} {} {}",
+            vector_to_text(&self.vertices[0]),
+            vector_to_text(&self.vertices[1]),
+            vector_to_text(&self.vertices[2]),
+            texture_to_text(&self.texture)
+        ))
+    }
+
+    fn validate(&self, checks: &mut Checks) {
+        let [a, b, c] = self.vertices;
+        checks
+            .point("vertices", &a)
+            .point("vertices", &b)
+            .point("vertices", &c)
+            .require(
+                (b - a).cross(&(c - a)).norm() > 0.0,
+                "vertices",
+                "must not lie on one line",
+            )
+            .texture(&self.texture);
+    }
+}
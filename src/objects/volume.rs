@@ -0,0 +1,221 @@
+use crate::color::RGB;
+use crate::objects::{Cube, Object, VoxelGrid};
+use crate::raytracer::{statistics, Ray};
+use crate::textures::Texture;
+use crate::type_aliases::{Color, Direction, Directions, Point};
+use nalgebra::Vector3;
+use rand::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// ### Medium
+///
+/// Homogeneous participating medium such as fog or smoke.
+///
+/// - `density` is the chance per unit of distance that a ray collides with a particle
+/// - `albedo` is the color of the particles, in the 0-255 range. Anything below white absorbs light
+/// - `anisotropy` is the Henyey-Greenstein `g`. Negative scatters light back, positive forward
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    pub density: f64,
+    pub albedo: Color,
+    pub anisotropy: f64,
+}
+
+impl Medium {
+    pub fn new(density: f64, albedo: Color, anisotropy: f64) -> Self {
+        Self {
+            density,
+            albedo,
+            anisotropy: anisotropy.clamp(-0.99, 0.99),
+        }
+    }
+
+    /// Sample how far a ray travels through the medium before it collides with a particle
+    pub fn sample_distance<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        if self.density <= 0.0 {
+            return f64::INFINITY;
+        }
+        -(1.0 - rng.gen::<f64>()).ln() / self.density
+    }
+
+    /// Henyey-Greenstein phase function, the density of light travelling along `incoming`
+    /// scattering towards `outgoing`
+    pub fn phase(&self, incoming: Direction, outgoing: Direction) -> f64 {
+        let g = self.anisotropy;
+        let cos_theta = incoming.normalize().dot(&outgoing.normalize());
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+
+    /// Sample the direction a ray travelling along `direction` scatters to
+    pub fn sample_phase<R: Rng + ?Sized>(&self, direction: Direction, rng: &mut R) -> Direction {
+        let g = self.anisotropy;
+        let (u, v): (f64, f64) = (rng.gen(), rng.gen());
+
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let term = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            (1.0 + g * g - term * term) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+
+        let local = Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        direction.normalize().to_world(&local)
+    }
+}
+
+/// ### Volume
+///
+/// A `Medium` filling the inside of any closed `Object`.
+///
+/// The boundary is only used to find where rays enter and leave the volume, it is never rendered
+/// itself. Add it to `Scene::volumes`, not `Scene::objects`.
+///
+/// Volumes made with `Volume::from_grid` vary their density with a `VoxelGrid`. In that case
+/// `medium.density` scales the densities stored in the grid.
+pub struct Volume {
+    pub boundary: Arc<dyn Object>,
+    pub medium: Medium,
+    pub grid: Option<GridDensity>,
+}
+
+/// A `VoxelGrid` stretched over an axis aligned box
+pub struct GridDensity {
+    pub grid: Arc<VoxelGrid>,
+    pub min_bounds: Point,
+    pub max_bounds: Point,
+}
+
+impl GridDensity {
+    pub fn density(&self, point: Point) -> f64 {
+        let local = (point - self.min_bounds).component_div(&(self.max_bounds - self.min_bounds));
+        self.grid.density(local)
+    }
+}
+
+impl Volume {
+    pub fn new(boundary: Arc<dyn Object>, medium: Medium) -> Self {
+        Self {
+            boundary,
+            medium,
+            grid: None,
+        }
+    }
+
+    /// Heterogeneous volume filling the cube at `center` with the densities of `grid`
+    pub fn from_grid(center: Point, size: f64, grid: Arc<VoxelGrid>, medium: Medium) -> Self {
+        let half_size = Point::repeat(size / 2.0);
+        let boundary = Cube::new(center, size, Texture::Diffusive(Color::white()));
+
+        Self {
+            boundary: Arc::new(boundary),
+            medium,
+            grid: Some(GridDensity {
+                grid,
+                min_bounds: center - half_size,
+                max_bounds: center + half_size,
+            }),
+        }
+    }
+
+    /// Distances along the ray where it enters and leaves the volume
+    pub fn span(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let probe = Ray::new(ray.origin, ray.direction, ray.depth);
+
+        if self.boundary.contains(&ray.origin) {
+            statistics::count_intersection_test(&self.boundary);
+            let exit = self.boundary.intersection(&probe)?;
+            return Some((0.0, exit.distance));
+        }
+
+        statistics::count_intersection_test(&self.boundary);
+        let entry = self.boundary.intersection(&probe)?.distance;
+        statistics::count_intersection_test(&self.boundary);
+        let inside = ray.origin + ray.direction * (entry + 1e-4);
+        let exit = self
+            .boundary
+            .intersection(&Ray::new(inside, ray.direction, ray.depth))?;
+
+        Some((entry, entry + 1e-4 + exit.distance))
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.boundary.contains(point)
+    }
+
+    /// Distance along the ray to the first particle it collides with, if that is closer than
+    /// `max_distance`.
+    ///
+    /// Heterogeneous volumes use delta tracking: collisions are sampled against the densest
+    /// voxel, and rejected in proportion to how much thinner the medium actually is there.
+    pub fn sample_distance<R: Rng + ?Sized>(
+        &self,
+        ray: &Ray,
+        max_distance: f64,
+        rng: &mut R,
+    ) -> Option<f64> {
+        let (entry, exit) = self.span(ray)?;
+        let exit = exit.min(max_distance);
+
+        let Some(grid) = &self.grid else {
+            let distance = entry + self.medium.sample_distance(rng);
+            return (distance < exit).then_some(distance);
+        };
+
+        // The loop below only ends for a finite majorant and exit
+        let majorant = self.medium.density * grid.grid.max_density();
+        if !majorant.is_finite() || majorant <= 0.0 || exit.is_nan() {
+            return None;
+        }
+
+        let mut distance = entry;
+        loop {
+            distance -= (1.0 - rng.gen::<f64>()).ln() / majorant;
+            if distance >= exit {
+                return None;
+            }
+
+            let density = self.medium.density * grid.density(ray.origin + ray.direction * distance);
+            if rng.gen::<f64>() < density / majorant {
+                return Some(distance);
+            }
+        }
+    }
+
+    /// Fraction of light that makes it through the volume along the ray, up to `max_distance`.
+    ///
+    /// Heterogeneous volumes are estimated with ratio tracking.
+    pub fn transmittance<R: Rng + ?Sized>(&self, ray: &Ray, max_distance: f64, rng: &mut R) -> f64 {
+        let Some((entry, exit)) = self.span(ray) else {
+            return 1.0;
+        };
+        let exit = exit.min(max_distance);
+        if entry >= exit {
+            return 1.0;
+        }
+
+        let Some(grid) = &self.grid else {
+            return (-self.medium.density * (exit - entry)).exp();
+        };
+
+        let majorant = self.medium.density * grid.grid.max_density();
+        if !majorant.is_finite() || majorant <= 0.0 || exit.is_nan() {
+            return 1.0;
+        }
+
+        let mut transmittance = 1.0;
+        let mut distance = entry;
+        loop {
+            distance -= (1.0 - rng.gen::<f64>()).ln() / majorant;
+            if distance >= exit {
+                return transmittance;
+            }
+
+            let density = self.medium.density * grid.density(ray.origin + ray.direction * distance);
+            transmittance *= 1.0 - density / majorant;
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::type_aliases::Point;
+use crate::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// ### VoxelGrid
+///
+/// Densities stored in a 3D grid, used by heterogeneous volumes to render clouds and explosions.
+///
+/// Voxels are stored with `x` changing fastest, then `y`, then `z`.
+///
+/// On disk a grid is a single header line `VOXELS <x> <y> <z>`, followed by every density as a
+/// little-endian `f32` in the same order.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    pub resolution: (usize, usize, usize),
+    pub densities: Vec<f32>,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    /// Grid of `densities`, which has to hold one for every voxel of `resolution`. Every side
+    /// needs at least one voxel.
+    pub fn new(resolution: (usize, usize, usize), densities: Vec<f32>) -> Result<Self, Error> {
+        let (x, y, z) = resolution;
+        if x == 0 || y == 0 || z == 0 {
+            return Err(Error::InvalidScene(format!(
+                "Voxel grid of {x}x{y}x{z} has no voxels"
+            )));
+        }
+        if voxel_count(resolution) != Some(densities.len()) {
+            return Err(Error::InvalidScene(format!(
+                "Voxel grid of {x}x{y}x{z} has {} densities",
+                densities.len()
+            )));
+        }
+
+        Ok(Self::from_densities(resolution, densities))
+    }
+
+    /// Grid of `densities` that are known to match `resolution`
+    fn from_densities(resolution: (usize, usize, usize), densities: Vec<f32>) -> Self {
+        let max_density = densities.iter().fold(0.0_f64, |max, d| max.max(*d as f64));
+        Self {
+            resolution,
+            densities,
+            max_density,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::read(path).map_err(Error::file(path))
+    }
+
+    fn read(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some("VOXELS") {
+            return Err(invalid("Missing VOXELS header"));
+        }
+
+        let mut dimension = || -> io::Result<usize> {
+            fields
+                .next()
+                .and_then(|field| field.parse().ok())
+                .filter(|size| *size > 0)
+                .ok_or_else(|| invalid("Invalid grid resolution"))
+        };
+        let resolution = (dimension()?, dimension()?, dimension()?);
+
+        let size = voxel_count(resolution)
+            .and_then(|count| count.checked_mul(4))
+            .ok_or_else(|| invalid("Grid resolution is too large"))?;
+
+        // Read through `take`, so a header claiming more voxels than the file holds does not
+        // allocate them all up front
+        let mut bytes = Vec::new();
+        reader.take(size as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "File holds fewer voxels than its header says",
+            ));
+        }
+
+        let densities = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).max(0.0))
+            .collect();
+
+        Ok(Self::from_densities(resolution, densities))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let write = || -> io::Result<()> {
+            let (x, y, z) = self.resolution;
+            let mut file = io::BufWriter::new(File::create(path)?);
+            writeln!(file, "VOXELS {x} {y} {z}")?;
+            for density in &self.densities {
+                file.write_all(&density.to_le_bytes())?;
+            }
+            file.flush()
+        };
+        write().map_err(Error::file(path))
+    }
+
+    /// Cloud-like grid made of fractal value noise, fading out towards the edges of the grid.
+    ///
+    /// `frequency` is the amount of noise features along one side of the grid.
+    pub fn from_noise(resolution: usize, seed: u64, frequency: f64) -> Self {
+        let size = resolution.max(2);
+        let mut densities = Vec::with_capacity(size * size * size);
+
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let local = Point::new(x as f64, y as f64, z as f64) / (size - 1) as f64;
+                    let falloff = 1.0 - ((local - Point::repeat(0.5)).norm() * 2.0).min(1.0);
+                    let noise = fractal_noise(local * frequency, seed);
+                    let density = (noise + falloff - 0.9) * 2.0;
+                    densities.push(density.max(0.0) as f32);
+                }
+            }
+        }
+
+        Self::from_densities((size, size, size), densities)
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    /// Trilinear interpolation of the density at `local`, with every axis in the 0.0-1.0 range
+    pub fn density(&self, local: Point) -> f64 {
+        let (size_x, size_y, size_z) = self.resolution;
+        let scale = |value: f64, size: usize| (value.clamp(0.0, 1.0) * (size - 1) as f64, size);
+        let (x, y, z) = (
+            scale(local.x, size_x),
+            scale(local.y, size_y),
+            scale(local.z, size_z),
+        );
+
+        let split = |(value, size): (f64, usize)| {
+            let low = (value.floor() as usize).min(size.saturating_sub(2));
+            let high = (low + 1).min(size - 1);
+            (low, high, value - low as f64)
+        };
+        let (x0, x1, tx) = split(x);
+        let (y0, y1, ty) = split(y);
+        let (z0, z1, tz) = split(z);
+
+        let voxel =
+            |x: usize, y: usize, z: usize| self.densities[x + size_x * (y + size_y * z)] as f64;
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let bottom = lerp(
+            lerp(voxel(x0, y0, z0), voxel(x1, y0, z0), tx),
+            lerp(voxel(x0, y1, z0), voxel(x1, y1, z0), tx),
+            ty,
+        );
+        let top = lerp(
+            lerp(voxel(x0, y0, z1), voxel(x1, y0, z1), tx),
+            lerp(voxel(x0, y1, z1), voxel(x1, y1, z1), tx),
+            ty,
+        );
+        lerp(bottom, top, tz)
+    }
+}
+
+/// Number of voxels in a grid of `resolution`, `None` when it does not fit in a `usize`
+fn voxel_count((x, y, z): (usize, usize, usize)) -> Option<usize> {
+    x.checked_mul(y)?.checked_mul(z)
+}
+
+/// Four octaves of value noise, roughly in the 0.0-1.0 range
+fn fractal_noise(point: Point, seed: u64) -> f64 {
+    let mut total = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+
+    for octave in 0..4 {
+        total += amplitude * value_noise(point * frequency, seed.wrapping_add(octave));
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    total / 0.9375
+}
+
+fn value_noise(point: Point, seed: u64) -> f64 {
+    let cell = point.map(f64::floor);
+    let t = (point - cell).map(|t| t * t * (3.0 - 2.0 * t));
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        lattice(
+            cell.x as i64 + dx,
+            cell.y as i64 + dy,
+            cell.z as i64 + dz,
+            seed,
+        )
+    };
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let bottom = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), t.x),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), t.x),
+        t.y,
+    );
+    let top = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), t.x),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), t.x),
+        t.y,
+    );
+    lerp(bottom, top, t.z)
+}
+
+/// Pseudo random value between 0.0 and 1.0 for a lattice point
+fn lattice(x: i64, y: i64, z: i64, seed: u64) -> f64 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+    use std::fs;
+
+    /// Error of loading a grid file made of `bytes`
+    fn load_error(name: &str, bytes: &[u8]) -> String {
+        let file = TempFile::new(&format!("{name}.vol"));
+        fs::write(&file.0, bytes).unwrap();
+        match VoxelGrid::load(&file.0) {
+            Ok(_) => panic!("{name} was loaded"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn saved_grids_load_the_same() {
+        let file = TempFile::new("round-trip.vol");
+        let grid = VoxelGrid::from_noise(5, 2, 3.0);
+        grid.save(&file.0).unwrap();
+
+        let loaded = VoxelGrid::load(&file.0).unwrap();
+        assert_eq!(loaded.resolution, grid.resolution);
+        assert_eq!(loaded.densities, grid.densities);
+        assert_eq!(loaded.max_density(), grid.max_density());
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        let error = load_error("header", b"VOXEL 1 1 1\n");
+        assert!(error.ends_with("Missing VOXELS header"), "{error}");
+        let error = load_error("zero", b"VOXELS 0 1 1\n");
+        assert!(error.ends_with("Invalid grid resolution"), "{error}");
+        let error = load_error("overflow", b"VOXELS 99999999999 99999999999 99999999999\n");
+        assert!(error.ends_with("Grid resolution is too large"), "{error}");
+
+        // Claims far more voxels than it holds, which must not be allocated up front
+        let error = load_error("short", b"VOXELS 100000 100000 100000\n\0\0\0\0");
+        assert!(
+            error.ends_with("fewer voxels than its header says"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn densities_have_to_match_the_resolution() {
+        assert!(VoxelGrid::new((2, 2, 1), vec![0.5; 4]).is_ok());
+        assert!(VoxelGrid::new((2, 2, 1), vec![0.5; 3]).is_err());
+        assert!(VoxelGrid::new((0, 2, 1), Vec::new()).is_err());
+        assert!(VoxelGrid::new((usize::MAX, 2, 1), Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use super::checkpoint::{read_bytes, read_u32};
+use super::{Camera, Image, PixelFormat, Ray, Scene};
+use crate::color::RGB;
+use crate::integrators::id_color;
+use crate::type_aliases::{Color, Normal};
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+
+/// ### Aov
+///
+/// Arbitrary output variable: a pass rendered next to the image, for compositing. The first
+/// five describe the first surface every camera ray hits, the last three split the light of the
+/// image.
+///
+/// - `Depth` is the distance from the camera to the surface, 0 where nothing was hit
+/// - `Normal` is the world-space normal, with its -1.0-1.0 range kept
+/// - `Albedo` is the color of the surface as a 0.0-1.0 factor, without any lighting
+/// - `ObjectId` gives every object in `Scene::objects` its own color
+/// - `MaterialId` gives objects with the same texture the same color
+/// - `Direct` is light the first surface receives straight from a light or the background
+/// - `Indirect` is the light that bounced more than once, and everything else
+/// - `Emission` is the light the camera sees straight from a light or the background
+///
+/// The three light passes add up to the image. Only the path tracer and the photon mapper split
+/// the light, other integrators put all of it in `Indirect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    ObjectId,
+    MaterialId,
+    Direct,
+    Indirect,
+    Emission,
+}
+
+/// Names accepted by `Aov::from_name`, in the order of `Aov::ALL`
+pub const AOVS: [&str; 8] = [
+    "depth",
+    "normal",
+    "albedo",
+    "object-id",
+    "material-id",
+    "direct",
+    "indirect",
+    "emission",
+];
+
+impl Aov {
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Emission,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let index = AOVS.iter().position(|aov| *aov == name)?;
+        Some(Aov::ALL[index])
+    }
+
+    pub fn name(self) -> &'static str {
+        let index = Aov::ALL.iter().position(|aov| *aov == self);
+        AOVS[index.expect("Every AOV is in Aov::ALL")]
+    }
+}
+
+/// Running sums of the AOVs of one pixel
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct AovEstimate {
+    samples: u32,
+    /// Samples that hit a surface, which the depth and the normal are averaged over
+    hits: u32,
+    depth: f64,
+    normal: Normal,
+    albedo: Color,
+    /// Indices of the first surface a sample hit, since ids can not be averaged
+    object_id: Option<usize>,
+    material_id: Option<usize>,
+    direct: Color,
+    indirect: Color,
+    emission: Color,
+}
+
+impl AovEstimate {
+    /// Add the first surface `ray` hit, which the integrator recorded while tracing it
+    fn add_first_hit(&mut self, ray: &Ray, scene: &Scene) {
+        let Some(hit) = ray.first_hit else {
+            return;
+        };
+
+        self.hits += 1;
+        self.depth += hit.distance;
+        self.normal += hit.normal;
+        self.albedo += hit.texture.albedo();
+        if self.object_id.is_none() {
+            let material_id = scene
+                .objects
+                .iter()
+                .position(|other| other.texture() == hit.texture)
+                .unwrap_or(hit.object);
+            self.object_id = Some(hit.object);
+            self.material_id = Some(material_id);
+        }
+    }
+
+    /// Add the light `ray` found, which came to `radiance` in total
+    fn add_light(&mut self, ray: &Ray, radiance: Color) {
+        self.samples += 1;
+        self.emission += ray.emitted;
+        self.direct += ray.direct;
+        self.indirect += radiance - ray.emitted - ray.direct;
+    }
+
+    /// Write the sums in the byte order of checkpoints, with `u64::MAX` for missing ids
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.samples.to_le_bytes())?;
+        writer.write_all(&self.hits.to_le_bytes())?;
+        writer.write_all(&self.depth.to_le_bytes())?;
+        for vector in [
+            self.normal,
+            self.albedo,
+            self.direct,
+            self.indirect,
+            self.emission,
+        ] {
+            for value in vector.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        for id in [self.object_id, self.material_id] {
+            let id = id.map_or(u64::MAX, |id| id as u64);
+            writer.write_all(&id.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let number = |reader: &mut R| read_bytes(reader).map(f64::from_le_bytes);
+        let vector = |reader: &mut R| -> io::Result<Color> {
+            Ok(Color::new(
+                number(reader)?,
+                number(reader)?,
+                number(reader)?,
+            ))
+        };
+        let id = |reader: &mut R| -> io::Result<Option<usize>> {
+            match u64::from_le_bytes(read_bytes(reader)?) {
+                u64::MAX => Ok(None),
+                id => Ok(Some(id as usize)),
+            }
+        };
+
+        Ok(Self {
+            samples: read_u32(reader)?,
+            hits: read_u32(reader)?,
+            depth: number(reader)?,
+            normal: vector(reader)?,
+            albedo: vector(reader)?,
+            direct: vector(reader)?,
+            indirect: vector(reader)?,
+            emission: vector(reader)?,
+            object_id: id(reader)?,
+            material_id: id(reader)?,
+        })
+    }
+
+    /// Value of `aov` for the pixel, on the 0-255 scale of the image for the light passes
+    fn value(&self, aov: Aov) -> Color {
+        let samples = self.samples.max(1) as f64;
+        let hits = self.hits.max(1) as f64;
+        let id_color = |id: Option<usize>| id.map_or(Color::black(), id_color) / 255.0;
+        match aov {
+            Aov::Depth => Color::repeat(self.depth / hits),
+            Aov::Normal => self.normal.try_normalize(0.0).unwrap_or_default(),
+            Aov::Albedo => self.albedo / samples,
+            Aov::ObjectId => id_color(self.object_id),
+            Aov::MaterialId => id_color(self.material_id),
+            Aov::Direct => self.direct / samples,
+            Aov::Indirect => self.indirect / samples,
+            Aov::Emission => self.emission / samples,
+        }
+    }
+}
+
+impl Camera {
+    /// Whether renders collect the AOVs, for themselves or for the denoiser
+    pub(crate) fn collects_aovs(&self) -> bool {
+        self.aovs || self.denoiser.is_some()
+    }
+
+    /// Make room for the AOVs of the next render, when it collects them. A resumed render goes
+    /// on with the AOVs of its checkpoint, every other render starts them over.
+    pub(crate) fn prepare_aovs(&mut self) {
+        let (width, height) = self.resolution;
+        let total_pixels = (width * height) as usize;
+        if !self.collects_aovs() {
+            self.aov_estimates = Vec::new();
+        } else if !(self.resumed && self.aov_estimates.len() == total_pixels) {
+            self.aov_estimates = (0..total_pixels).map(|_| Mutex::default()).collect();
+        }
+    }
+
+    /// Trace the camera ray of one sample of `pixel`, and add what it found to the AOVs when the
+    /// render collects them
+    pub(crate) fn trace_sample(
+        &self,
+        scene: &Scene,
+        pixel: usize,
+        ray: &mut Ray,
+        trace: impl FnOnce(&mut Ray) -> Color,
+    ) -> Color {
+        let radiance = trace(ray);
+        if let Some(aov) = self.aov_estimates.get(pixel) {
+            let mut aov = aov.lock().expect("AOVs are not poisoned");
+            aov.add_first_hit(ray, scene);
+            aov.add_light(ray, radiance);
+        }
+        radiance
+    }
+
+    /// `aov` of the last render, with the exposure applied to the light passes. `None` when the
+    /// render did not collect AOVs, see `CameraBuilder::aovs`.
+    pub fn aov_image(&self, aov: Aov) -> Option<Image> {
+        if self.aov_estimates.is_empty() {
+            return None;
+        }
+
+        let (width, height) = self.resolution;
+        let mut image = Image::new(width, height, PixelFormat::Rgb);
+        let scale = match aov {
+            Aov::Direct | Aov::Indirect | Aov::Emission => 2f64.powf(self.exposure) / 255.0,
+            _ => 1.0,
+        };
+
+        let pixels = image.data.chunks_exact_mut(3);
+        for (pixel, estimate) in pixels.zip(&self.aov_estimates) {
+            let estimate = estimate.lock().expect("AOVs are not poisoned");
+            let value = estimate.value(aov) * scale;
+            pixel[0] = value.x as f32;
+            pixel[1] = value.y as f32;
+            pixel[2] = value.z as f32;
+        }
+        Some(image)
+    }
+}
@@ -1,9 +1,15 @@
+use super::aov::AovEstimate;
+use super::checkpoint::Checkpoint;
+use super::denoise::Denoiser;
+use super::distributed;
+use super::statistics::{self, Recorder, RenderStats};
 use crate::config::camera::*;
 use crate::type_aliases::{Color, Direction};
 
 #[derive(Debug)]
 pub struct Camera {
     pub sample_size: u16,
+    pub max_depth: u32,
     pub position: Vector3<f64>,
     pub look_at: Vector3<f64>,
     pub up_direction: Vector3<f64>,
@@ -11,58 +17,666 @@ pub struct Camera {
     pub aspect_ratio: f64,
     pub focal_length: f64,
     pub sensor_width: f64,
+    pub exposure: f64,
+    pub integrator: Arc<dyn Integrator>,
+    pub sampler: Arc<dyn Sampler>,
+    pub seed: u64,
+    /// Noise at which adaptive sampling stops sampling a pixel, `None` samples every pixel equally
+    pub noise_threshold: Option<f64>,
+    pub max_sample_size: u16,
+    pub tile_size: u32,
+    pub tile_order: TileOrder,
+    pub progress: Option<ProgressCallback>,
+    /// File the render is saved to while it runs, so it can be resumed
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+    /// Why the last render could not save its checkpoint, `None` when every save worked
+    pub checkpoint_error: Option<Error>,
+    /// Workers that failed in the last distributed render, see `Error::Worker`
+    pub worker_errors: Vec<Error>,
     pub pixels: Pixels,
+    /// Samples every pixel took in the last render
+    pub sample_counts: Vec<u32>,
+    /// Rays and times of the last render
+    pub stats: RenderStats,
+    /// Whether renders collect the AOVs, see `Camera::aov_image`
+    pub aovs: bool,
+    /// Filter the image with at the end of every render, which also collects the AOVs it needs
+    pub denoiser: Option<Denoiser>,
+    /// Sums of the samples of every pixel in the last render
+    pub(crate) estimates: Vec<PixelEstimate>,
+    /// Whether the next render continues from `estimates`, which only `resume` asks for
+    pub(crate) resumed: bool,
+    /// Sums of the AOVs of every pixel, empty when the last render did not collect them
+    pub(crate) aov_estimates: Vec<Mutex<AovEstimate>>,
+    /// First checkpoint that could not be saved while rendering, for `checkpoint_error`
+    save_error: Mutex<Option<Error>>,
+}
+
+/// ### Progressive
+///
+/// Settings for `Camera::send_rays_progressive`. The render takes `pass_size` samples per pixel
+/// at a time, and stops early when `time_budget` runs out or when `cancel` is set from another
+/// thread.
+#[derive(Debug, Clone)]
+pub struct Progressive {
+    pub pass_size: u32,
+    pub time_budget: Option<Duration>,
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Progressive {
+    pub fn new(pass_size: u32) -> Self {
+        Self {
+            pass_size,
+            time_budget: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Stop the render as soon as `cancel` is set
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+}
+
+impl Default for Progressive {
+    fn default() -> Self {
+        Self::new(DEFAULT_PASS_SIZE)
+    }
+}
+
+/// Progress of a progressive render after one of its passes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    /// Passes done so far, starting at 1
+    pub number: u32,
+    /// Samples taken so far, over all pixels
+    pub samples: u64,
+    pub elapsed: Duration,
+}
+
+/// Why a progressive render stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderEnd {
+    /// Every pixel has its samples
+    Finished,
+    OutOfTime,
+    Cancelled,
+}
+
+/// One pass over the tiles, and how much of the whole render it is
+struct Sweep<'a> {
+    /// Samples every active pixel takes
+    batch: u64,
+    pass: u32,
+    start: Instant,
+    /// Part of the render done before this pass, and the part this pass adds
+    done: f64,
+    share: f64,
+    time_budget: Option<Duration>,
+    /// Pixels that have not started when this turns true are skipped
+    should_stop: &'a (dyn Fn() -> bool + Sync),
+}
+
+impl<'a> Sweep<'a> {
+    fn new(batch: u64, start: Instant) -> Self {
+        Self {
+            batch,
+            pass: 1,
+            start,
+            done: 0.0,
+            share: 1.0,
+            time_budget: None,
+            should_stop: &|| false,
+        }
+    }
+}
+
+/// Running sums of the samples of one pixel
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct PixelEstimate {
+    pub total: Color,
+    pub luminance: f64,
+    pub luminance_squared: f64,
+    pub samples: u32,
+}
+
+impl PixelEstimate {
+    fn add(&mut self, color: Color) {
+        let luminance = 0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z;
+        self.total += color;
+        self.luminance += luminance;
+        self.luminance_squared += luminance * luminance;
+        self.samples += 1;
+    }
+
+    fn mean(&self) -> Color {
+        match self.samples {
+            0 => Color::black(),
+            samples => self.total / samples as f64,
+        }
+    }
+
+    /// Standard error of the mean brightness, relative to the brightness
+    fn noise(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        let mean = self.luminance / self.samples as f64;
+        self.variance().sqrt() / mean.max(ADAPTIVE_MIN_LUMINANCE)
+    }
+
+    /// Variance of the mean brightness, infinite with fewer than two samples
+    pub(crate) fn variance(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+
+        let samples = self.samples as f64;
+        let mean = self.luminance / samples;
+        (self.luminance_squared / samples - mean * mean).max(0.0) / (samples - 1.0)
+    }
 }
 
 impl Camera {
     pub fn send_rays(&mut self, scene: Arc<Scene>) {
+        let mut recorder = Recorder::start();
+        self.integrator.prepare(&scene, self.seed);
+        self.prepare_aovs();
+        recorder.end_phase("prepare");
+
+        let mut estimates = self.take_estimates();
+        let start = Instant::now();
+        match self.noise_threshold {
+            None => self.sample_evenly(&scene, &mut estimates, start),
+            Some(_) => self.sample_adaptively(&scene, &mut estimates, start),
+        }
+        recorder.end_phase("render");
+
+        self.finish(estimates);
+        recorder.end_phase("finish");
+        self.stats = recorder.finish();
+    }
+
+    /// Render on the worker processes listening at `workers`, see `serve_worker`. Every worker
+    /// gets the scene and the camera as a scene file, and then renders one tile at a time until
+    /// there are none left. Tiles of a worker that fails go to the others, and its error to
+    /// `worker_errors`.
+    ///
+    /// The image is the same as with `send_rays`, except that adaptive sampling has no budget to
+    /// share between the tiles, so every pixel samples until it is below the noise threshold.
+    /// Workers rebuild the integrator from its name and `Integrator::settings`.
+    pub fn send_rays_distributed<A: ToSocketAddrs>(
+        &mut self,
+        scene: Arc<Scene>,
+        workers: &[A],
+    ) -> Result<(), Error> {
+        let mut recorder = Recorder::start();
+        let job = scene_to_text(&scene, self)?;
+
+        // The workers only send back the image
+        self.aov_estimates = Vec::new();
+
+        let (width, _) = self.resolution;
+        let estimates = self.take_estimates();
+        let is_unfinished = self.unfinished_filter();
+        let tiles: Vec<Tile> = split_into_tiles(self.resolution, self.tile_size, self.tile_order)
+            .into_iter()
+            .filter(|tile| {
+                tile.pixels(width)
+                    .any(|pixel| is_unfinished(&estimates[pixel]))
+            })
+            .collect();
+
+        let total = (self.max_samples() * estimates.len() as u64).max(1);
+        let spent: u64 = estimates.iter().map(|e| e.samples as u64).sum();
+        let sweep = Sweep {
+            done: spent as f64 / total as f64,
+            share: 1.0 - spent as f64 / total as f64,
+            ..Sweep::new(self.max_samples(), Instant::now())
+        };
+
+        let estimates = Mutex::new(estimates);
+        let tiles_done = AtomicUsize::new(0);
+        let last_save = Mutex::new(Instant::now());
+        let on_tile = || {
+            let done = tiles_done.fetch_add(1, Ordering::Relaxed) + 1;
+            self.report(&sweep, done, tiles.len());
+
+            let mut last_save = last_save.lock().expect("Save time is not poisoned");
+            if last_save.elapsed() >= self.checkpoint_interval {
+                let estimates = estimates.lock().expect("Estimates are not poisoned");
+                self.save_periodically(&estimates, &mut last_save);
+            }
+        };
+        let failures = Mutex::new(Vec::new());
+        let rendered = distributed::render_tiles(
+            workers, &job, width, &tiles, &estimates, &on_tile, &failures,
+        );
+        self.worker_errors = failures.into_inner().expect("Failures are not poisoned");
+        recorder.end_phase("render");
+
+        // Keep what the workers finished even when the render failed, so it can be resumed
+        self.finish(estimates.into_inner().expect("Estimates are not poisoned"));
+        recorder.end_phase("finish");
+        self.stats = recorder.finish();
+        Ok(rendered?)
+    }
+
+    /// Take the samples the pixels of `tile` are still missing, for distributed workers
+    pub(crate) fn sample_tile(&self, scene: &Scene, tile: &Tile, estimates: &mut [PixelEstimate]) {
+        let (width, _) = self.resolution;
+        let is_unfinished = self.unfinished_filter();
+        let first_batch = match self.noise_threshold {
+            Some(_) => self.min_adaptive_samples() as u64,
+            None => self.sample_size as u64,
+        };
+
+        let pixels: Vec<usize> = tile.pixels(width).collect();
+        pixels
+            .into_par_iter()
+            .zip(estimates.par_iter_mut())
+            .for_each(|(pixel, estimate)| {
+                let mut batch = first_batch;
+                while is_unfinished(estimate) {
+                    self.sample_pixel(scene, pixel, estimate, batch, self.max_samples());
+                    batch = ADAPTIVE_BATCH;
+                }
+            });
+    }
+
+    /// Continue from the checkpoint at `path`: the next render keeps the samples in it and only
+    /// adds the ones that are missing, for example after raising the sample size. The resolution,
+    /// the sampler and the integrator have to match the checkpoint, and a camera that collects
+    /// AOVs needs a checkpoint that has them.
+    ///
+    /// Uses the seed of the checkpoint, so with the same sample size the result is the same as a
+    /// render that was never interrupted. The stratified and Halton samplers spread the samples
+    /// of a pixel over its sample size, so after raising it the image differs from a render that
+    /// had the higher sample size from the start, although it is just as correct.
+    pub fn resume<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let checkpoint = Checkpoint::read(path.as_ref())?;
+        if checkpoint.resolution != self.resolution {
+            let (width, height) = checkpoint.resolution;
+            return Err(Error::Checkpoint(format!(
+                "Checkpoint is {width}x{height}, which does not match the camera"
+            )));
+        }
+        if checkpoint.sampler != self.sampler.name() {
+            return Err(Error::Checkpoint(format!(
+                "Checkpoint uses the {} sampler, which does not match the camera",
+                checkpoint.sampler
+            )));
+        }
+        if checkpoint.integrator != self.integrator.name() {
+            return Err(Error::Checkpoint(format!(
+                "Checkpoint uses the {} integrator, which does not match the camera",
+                checkpoint.integrator
+            )));
+        }
+        if self.collects_aovs() && checkpoint.aovs.is_empty() {
+            return Err(Error::Checkpoint(
+                "Checkpoint has no AOVs, which the camera collects".to_string(),
+            ));
+        }
+
+        self.seed = checkpoint.seed;
+        self.store(&checkpoint.estimates);
+        self.estimates = checkpoint.estimates;
+        self.aov_estimates = checkpoint.aovs.into_iter().map(Mutex::new).collect();
+        self.resumed = true;
+        Ok(())
+    }
+
+    /// Write the samples of the last render to `path`, for `resume`
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        Checkpoint::write(path, self, &self.estimates).map_err(Error::file(path))
+    }
+
+    /// Sums of the samples of the resumed checkpoint, or empty ones for a new render
+    fn take_estimates(&mut self) -> Vec<PixelEstimate> {
         let (width, height) = self.resolution;
         let total_pixels = (width * height) as usize;
+        match std::mem::take(&mut self.estimates) {
+            estimates if self.resumed && estimates.len() == total_pixels => estimates,
+            _ => vec![PixelEstimate::default(); total_pixels],
+        }
+    }
+
+    /// Keep the sums of a finished render, denoise it, and save its last checkpoint. A failed
+    /// save goes to `checkpoint_error`, before one that failed while rendering.
+    fn finish(&mut self, estimates: Vec<PixelEstimate>) {
+        self.store(&estimates);
+        self.estimates = estimates;
+        self.resumed = false;
+        if let Some(denoiser) = self.denoiser {
+            self.denoise(&denoiser);
+        }
 
-        // Pre-allocate a vector with default Color values
-        let mut colors = vec![Vector3::default(); total_pixels];
+        let saved = match &self.checkpoint {
+            Some(path) => self.save_checkpoint(path),
+            None => Ok(()),
+        };
+        let save_error = self
+            .save_error
+            .get_mut()
+            .expect("Save error is not poisoned");
+        self.checkpoint_error = saved.err().or(save_error.take());
+    }
 
-        // Parallelize the computation for each pixel
-        colors
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(pixel, pixel_color)| {
-                let column = pixel as u32 % width;
-                let row = (total_pixels - pixel) as u32 / width;
-                let mut total_color = Color::black();
+    /// Save a checkpoint when the last one is older than the checkpoint interval
+    fn save_periodically(&self, estimates: &[PixelEstimate], last_save: &mut Instant) {
+        let Some(path) = &self.checkpoint else {
+            return;
+        };
+        if last_save.elapsed() < self.checkpoint_interval {
+            return;
+        }
 
-                for _sample in 0..self.sample_size {
-                    let direction = self.ray_direction(column, row);
-                    let mut ray = Ray::new(self.position, direction, 0);
+        *last_save = Instant::now();
+        if let Err(error) = Checkpoint::write(path, self, estimates) {
+            let mut save_error = self.save_error.lock().expect("Save error is not poisoned");
+            save_error.get_or_insert(Error::file(path)(error));
+        }
+    }
 
-                    ray.trace(&scene); // Recursive ray tracing with default 50 depth.
+    /// Take `sample_size` samples in every pixel. Renders with checkpoints go in passes, so there
+    /// is something to save in between.
+    fn sample_evenly(&self, scene: &Scene, estimates: &mut [PixelEstimate], start: Instant) {
+        let is_unfinished = self.unfinished_filter();
+        let batch = match self.checkpoint {
+            Some(_) => CHECKPOINT_PASS_SIZE,
+            None => self.sample_size as u64,
+        };
 
-                    if ray.collisions.is_empty() {
-                        total_color += scene.background(); // No collision, add background color.
-                        continue;
-                    }
+        let total = self.sample_size as u64 * estimates.len() as u64;
+        let mut last_save = Instant::now();
+        for pass in 1.. {
+            if !estimates.par_iter().any(&is_unfinished) {
+                break;
+            }
+
+            let spent: u64 = estimates.iter().map(|e| e.samples as u64).sum();
+            let sweep = Sweep {
+                pass,
+                done: spent as f64 / total as f64,
+                share: (batch * estimates.len() as u64).min(total - spent) as f64 / total as f64,
+                ..Sweep::new(batch, start)
+            };
+            self.sample_pixels(scene, estimates, &sweep, &is_unfinished);
+            self.save_periodically(estimates, &mut last_save);
+        }
+    }
+
+    /// Render in passes of `pass_size` samples per pixel, calling `on_pass` with the image so far
+    /// after every pass. Stops once every pixel has its samples, or earlier when the time budget
+    /// runs out or the render is cancelled; a pass that is cut short still reaches `on_pass`.
+    ///
+    /// Gives the same image as `send_rays` when it runs to the end. With a noise threshold, pixels
+    /// stop once they are below it, but there is no budget of samples to hand out: noisy pixels
+    /// take up to the maximum sample size.
+    pub fn send_rays_progressive<F>(
+        &mut self,
+        scene: Arc<Scene>,
+        progressive: &Progressive,
+        mut on_pass: F,
+    ) -> RenderEnd
+    where
+        F: FnMut(&Camera, &Pass),
+    {
+        let start = Instant::now();
+        let deadline = progressive.time_budget.map(|budget| start + budget);
+        let stop_reason = || {
+            if progressive.cancel.load(Ordering::Relaxed) {
+                Some(RenderEnd::Cancelled)
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                Some(RenderEnd::OutOfTime)
+            } else {
+                None
+            }
+        };
+        let should_stop = || stop_reason().is_some();
+
+        let mut recorder = Recorder::start();
+        self.integrator.prepare(&scene, self.seed);
+        self.prepare_aovs();
+        recorder.end_phase("prepare");
+
+        let mut estimates = self.take_estimates();
+        let is_unfinished = self.unfinished_filter();
+        let batch = progressive.pass_size.max(1) as u64;
+        let total = (self.max_samples() * estimates.len() as u64).max(1);
+
+        let mut last_save = Instant::now();
+        let mut end = RenderEnd::Finished;
+        for number in 1.. {
+            if let Some(reason) = stop_reason() {
+                end = reason;
+                break;
+            }
+            if !estimates.par_iter().any(&is_unfinished) {
+                break;
+            }
+
+            let spent: u64 = estimates.iter().map(|e| e.samples as u64).sum();
+            let sweep = Sweep {
+                pass: number,
+                done: (spent as f64 / total as f64).min(1.0),
+                share: (batch * estimates.len() as u64) as f64 / total as f64,
+                time_budget: progressive.time_budget,
+                should_stop: &should_stop,
+                ..Sweep::new(batch, start)
+            };
+            self.sample_pixels(&scene, &mut estimates, &sweep, &is_unfinished);
+            self.store(&estimates);
+            self.save_periodically(&estimates, &mut last_save);
+
+            let pass = Pass {
+                number,
+                samples: estimates.iter().map(|e| e.samples as u64).sum(),
+                elapsed: start.elapsed(),
+            };
+            on_pass(self, &pass);
+        }
+        recorder.end_phase("render");
+
+        self.finish(estimates);
+        recorder.end_phase("finish");
+        self.stats = recorder.finish();
+        end
+    }
+
+    /// Whether a pixel still needs samples, without a budget to share between the pixels
+    fn unfinished_filter(&self) -> impl Fn(&PixelEstimate) -> bool + Sync {
+        let sample_size = self.sample_size as u32;
+        let max_samples = self.max_sample_size as u32;
+        let min_samples = self.min_adaptive_samples();
+        let threshold = self.noise_threshold;
+
+        move |estimate: &PixelEstimate| match threshold {
+            None => estimate.samples < sample_size,
+            Some(threshold) => {
+                estimate.samples < max_samples
+                    && (estimate.samples < min_samples || estimate.noise() > threshold)
+            }
+        }
+    }
+
+    /// Most samples a pixel can take
+    fn max_samples(&self) -> u64 {
+        match self.noise_threshold {
+            Some(_) => self.max_sample_size as u64,
+            None => self.sample_size as u64,
+        }
+    }
+
+    /// Samples every pixel takes before adaptive sampling judges its noise. Paths that rarely find
+    /// the light can look noiseless after a few samples.
+    fn min_adaptive_samples(&self) -> u32 {
+        (self.sample_size as u32 / ADAPTIVE_MIN_DIVISOR).max(ADAPTIVE_MIN_SAMPLES)
+    }
+
+    /// Set every pixel to the average color of its samples
+    fn store(&mut self, estimates: &[PixelEstimate]) {
+        self.pixels = estimates.iter().map(PixelEstimate::mean).collect();
+        self.sample_counts = estimates.iter().map(|estimate| estimate.samples).collect();
+    }
 
-                    if ray.hit_light_source {
-                        total_color += ray.average_color(&scene);
-                    } else {
-                        total_color += ray.average_color(&scene) * scene.brightness
+    /// Spend the budget of `sample_size` samples per pixel on the pixels that are still noisy.
+    /// Pixels stop once their noise is below the threshold, or when they reach the maximum sample
+    /// size.
+    fn sample_adaptively(&self, scene: &Scene, estimates: &mut [PixelEstimate], start: Instant) {
+        let is_noisy = self.unfinished_filter();
+
+        // A resumed render only has the samples it has not spent yet
+        let total_budget = self.sample_size as u64 * estimates.len() as u64;
+        let spent: u64 = estimates.iter().map(|e| e.samples as u64).sum();
+        let mut budget = total_budget.saturating_sub(spent);
+        let mut batch = self.min_adaptive_samples() as u64;
+        let mut last_save = Instant::now();
+        for pass in 1.. {
+            let noisy = estimates.par_iter().filter(|e| is_noisy(e)).count() as u64;
+            if noisy == 0 || budget == 0 {
+                break;
+            }
+
+            batch = batch.min(budget.div_ceil(noisy));
+            let sweep = Sweep {
+                pass,
+                done: 1.0 - budget as f64 / total_budget as f64,
+                share: (noisy * batch).min(budget) as f64 / total_budget as f64,
+                ..Sweep::new(batch, start)
+            };
+            self.sample_pixels(scene, estimates, &sweep, &is_noisy);
+            self.save_periodically(estimates, &mut last_save);
+            budget = budget.saturating_sub(noisy * batch);
+            batch = ADAPTIVE_BATCH;
+        }
+    }
+
+    /// Add up to `batch` samples to every pixel that `is_active`, one tile at a time
+    fn sample_pixels<F>(
+        &self,
+        scene: &Scene,
+        estimates: &mut [PixelEstimate],
+        sweep: &Sweep,
+        is_active: F,
+    ) where
+        F: Fn(&PixelEstimate) -> bool + Sync,
+    {
+        let (width, _) = self.resolution;
+        let max_samples = self.max_samples();
+
+        // Every tile works on its own copy of its pixels, which are copied back at the end
+        let tiles = split_into_tiles(self.resolution, self.tile_size, self.tile_order);
+        let tile_estimates: Vec<Mutex<Vec<PixelEstimate>>> = tiles
+            .iter()
+            .map(|tile| Mutex::new(tile.pixels(width).map(|pixel| estimates[pixel]).collect()))
+            .collect();
+        let next_tile = AtomicUsize::new(0);
+        let tiles_done = AtomicUsize::new(0);
+
+        // Every thread takes the next tile in order, until there are none left
+        (0..rayon::current_num_threads())
+            .into_par_iter()
+            .for_each(|_| loop {
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                let Some(tile) = tiles.get(index) else {
+                    break;
+                };
+
+                let mut tile_estimates =
+                    tile_estimates[index].lock().expect("Tile is not poisoned");
+                for (pixel, estimate) in tile.pixels(width).zip(tile_estimates.iter_mut()) {
+                    if is_active(estimate) && !(sweep.should_stop)() {
+                        self.sample_pixel(scene, pixel, estimate, sweep.batch, max_samples);
                     }
                 }
 
-                // Set the current pixel to the average color of the samples.
-                *pixel_color = total_color / self.sample_size as f64;
+                let done = tiles_done.fetch_add(1, Ordering::Relaxed) + 1;
+                self.report(sweep, done, tiles.len());
+            });
+
+        for (tile, tile_estimates) in tiles.iter().zip(tile_estimates) {
+            let tile_estimates = tile_estimates.into_inner().expect("Tile is not poisoned");
+            for (pixel, estimate) in tile.pixels(width).zip(tile_estimates) {
+                estimates[pixel] = estimate;
+            }
+        }
+    }
+
+    /// Add up to `batch` samples to `pixel`, without going over `max_samples`
+    fn sample_pixel(
+        &self,
+        scene: &Scene,
+        pixel: usize,
+        estimate: &mut PixelEstimate,
+        batch: u64,
+        max_samples: u64,
+    ) {
+        let (width, height) = self.resolution;
+        let column = pixel as u32 % width;
+        let row = ((width * height) as usize - pixel) as u32 / width;
+
+        let first = estimate.samples as u64;
+        let last = (first + batch).min(max_samples);
+        statistics::count_camera_rays(last.saturating_sub(first));
+        for index in first..last {
+            // Every sample has its own numbers, independent of the thread tracing it
+            let sample = PixelSample {
+                seed: self.seed,
+                pixel: (column, row),
+                index,
+                count: max_samples,
+            };
+            let mut rng = Samples::new(self.sampler.as_ref(), sample);
+            let direction = self.ray_direction(column, row, &mut rng);
+            let mut ray = Ray::new(self.position, direction, 0);
+            ray.max_depth = self.max_depth;
+
+            let radiance = self.trace_sample(scene, pixel, &mut ray, |ray| {
+                self.integrator.radiance(ray, scene, &mut rng)
             });
+            estimate.add(radiance);
+        }
+    }
+
+    /// Hand the progress after `tiles_done` tiles of `sweep` to the progress callback
+    fn report(&self, sweep: &Sweep, tiles_done: usize, tiles_total: usize) {
+        let Some(ProgressCallback(callback)) = &self.progress else {
+            return;
+        };
 
-        // Update the camera's pixels
-        self.pixels = colors;
+        let elapsed = sweep.start.elapsed();
+        let mut done = sweep.done + sweep.share * tiles_done as f64 / tiles_total as f64;
+        if let Some(budget) = sweep.time_budget {
+            done = done.max(elapsed.as_secs_f64() / budget.as_secs_f64());
+        }
+        let done = done.clamp(0.0, 1.0);
+
+        callback(&Progress {
+            pass: sweep.pass,
+            tiles_done,
+            tiles_total,
+            done,
+            elapsed,
+            eta: (done > 0.0).then(|| elapsed.mul_f64((1.0 - done) / done)),
+        });
     }
 
-    pub fn write_to_ppm(&self, path: &str) {
+    pub fn write_to_ppm<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
         let (w, h) = self.resolution;
-        let mut file = std::fs::File::create(path).unwrap();
-        writeln!(file, "P3").unwrap();
-        writeln!(file, "{w} {h}").unwrap();
-        writeln!(file, "255").unwrap();
+        let exposure = 2f64.powf(self.exposure);
         let pixel_data: Vec<String> = self
             .pixels
             .par_iter()
@@ -70,7 +684,7 @@ impl Camera {
             .map(|row| {
                 row.iter()
                     .map(|pixel| {
-                        let corrected = pixel.correct_gamma(2.0);
+                        let corrected = (*pixel * exposure).correct_gamma(2.0);
                         format!("{} {} {}", corrected.r(), corrected.g(), corrected.b())
                     })
                     .collect::<Vec<String>>()
@@ -79,18 +693,25 @@ impl Camera {
             .collect();
 
         // Write the prepared pixel data to the file
-        for row in pixel_data {
-            writeln!(file, "{}", row).unwrap();
-        }
+        let write = || -> io::Result<()> {
+            let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+            writeln!(file, "P3")?;
+            writeln!(file, "{w} {h}")?;
+            writeln!(file, "255")?;
+            for row in pixel_data {
+                writeln!(file, "{}", row)?;
+            }
+            file.flush()
+        };
+        write().map_err(Error::file(path))
     }
 
-    fn ray_direction(&self, pixel_x: u32, pixel_y: u32) -> Vector3<f64> {
+    fn ray_direction<R: Rng>(&self, pixel_x: u32, pixel_y: u32, rand: &mut R) -> Vector3<f64> {
         // Calculate the camera basis vectors
         let view_direction = (self.position - self.look_at).normalize();
         let right_vector = self.up_direction.cross(&view_direction).normalize();
         let up_vector = view_direction.cross(&right_vector);
         let (width, height) = self.resolution;
-        let mut rand = rand::thread_rng();
 
         // Convert pixel coordinates to normalized world coordinates
         let normalized_x = (pixel_x as f64 + rand.gen_range(0.0..1.0)) / (width as f64) - 0.5;
@@ -102,35 +723,65 @@ impl Camera {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct CameraBuilder {
     pub sample_size: Option<u16>,
+    pub max_depth: Option<u32>,
     pub position: Option<Vector3<f64>>,
     pub look_at: Option<Vector3<f64>>,
     pub up_direction: Option<Vector3<f64>>,
     pub resolution: Option<Resolution>,
     pub focal_length: Option<f64>,
     pub sensor_width: Option<f64>,
+    pub exposure: Option<f64>,
+    pub integrator: Option<Arc<dyn Integrator>>,
+    pub sampler: Option<Arc<dyn Sampler>>,
+    pub seed: Option<u64>,
+    pub noise_threshold: Option<f64>,
+    pub max_sample_size: Option<u16>,
+    pub tile_size: Option<u32>,
+    pub tile_order: Option<TileOrder>,
+    pub progress: Option<ProgressCallback>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Option<Duration>,
+    pub aovs: bool,
+    pub denoiser: Option<Denoiser>,
 }
 
 impl CameraBuilder {
     pub fn new() -> Self {
         Self {
             sample_size: None,
+            max_depth: None,
             position: None,
             look_at: None,
             up_direction: None,
             resolution: None,
             focal_length: None,
             sensor_width: None,
+            exposure: None,
+            integrator: None,
+            sampler: None,
+            seed: None,
+            noise_threshold: None,
+            max_sample_size: None,
+            tile_size: None,
+            tile_order: None,
+            progress: None,
+            checkpoint: None,
+            checkpoint_interval: None,
+            aovs: false,
+            denoiser: None,
         }
     }
 
     pub fn build(&self) -> Camera {
         let (width, height) = self.resolution.unwrap_or(DEFAULT_RESOLUTION);
+        let sample_size = self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE);
 
         Camera {
-            sample_size: self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE),
+            sample_size,
+            max_depth: self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
             position: self.position.unwrap_or(DEFAULT_CAMERA_POSITION),
             look_at: self.look_at.unwrap_or_default(), // 0,0,0 is the default
             up_direction: self.adjusted_up_direction(),
@@ -138,7 +789,35 @@ impl CameraBuilder {
             aspect_ratio: width as f64 / height as f64,
             focal_length: self.focal_length.unwrap_or(DEFAULT_FOCAL_LENGTH),
             sensor_width: self.sensor_width.unwrap_or(DEFAULT_SENSOR_WIDTH),
+            exposure: self.exposure.unwrap_or(DEFAULT_EXPOSURE),
+            integrator: self
+                .integrator
+                .clone()
+                .unwrap_or_else(|| Arc::new(PathTracer)),
+            sampler: self.sampler.clone().unwrap_or_else(|| Arc::new(Sobol)),
+            seed: self.seed.unwrap_or_else(rand::random),
+            noise_threshold: self.noise_threshold,
+            max_sample_size: self
+                .max_sample_size
+                .unwrap_or(sample_size.saturating_mul(ADAPTIVE_MAX_FACTOR)),
+            tile_size: self.tile_size.unwrap_or(DEFAULT_TILE_SIZE),
+            tile_order: self.tile_order.unwrap_or_default(),
+            progress: self.progress.clone(),
+            checkpoint: self.checkpoint.clone(),
+            checkpoint_interval: self
+                .checkpoint_interval
+                .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL),
+            checkpoint_error: None,
+            worker_errors: Vec::new(),
             pixels: Vec::new(),
+            sample_counts: Vec::new(),
+            stats: RenderStats::default(),
+            aovs: self.aovs,
+            denoiser: self.denoiser,
+            estimates: Vec::new(),
+            resumed: false,
+            aov_estimates: Vec::new(),
+            save_error: Mutex::default(),
         }
     }
 
@@ -175,6 +854,102 @@ impl CameraBuilder {
         self
     }
 
+    /// Maximum amount of bounces per path. Russian roulette usually ends paths well before that.
+    pub fn max_depth(&mut self, max_depth: u32) -> &mut Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// How the light arriving at the camera is computed. Defaults to the `PathTracer`.
+    pub fn integrator(&mut self, integrator: Arc<dyn Integrator>) -> &mut Self {
+        self.integrator = Some(integrator);
+        self
+    }
+
+    /// How the numbers of every sample are picked. Uses `Sobol` when not set.
+    pub fn sampler(&mut self, sampler: Arc<dyn Sampler>) -> &mut Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Seed of the random numbers. Renders with the same seed and settings are identical,
+    /// without one every render picks a random seed.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Turn on adaptive sampling. Pixels stop taking samples once the standard error of their
+    /// brightness is below `threshold` times the brightness, and the samples they save go to
+    /// noisier pixels. The render still takes `sample_size` samples per pixel on average.
+    pub fn noise_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.noise_threshold = Some(threshold);
+        self
+    }
+
+    /// Most samples a single pixel takes with adaptive sampling. Defaults to four times the
+    /// sample size.
+    pub fn max_sample_size(&mut self, max_sample_size: u16) -> &mut Self {
+        self.max_sample_size = Some(max_sample_size);
+        self
+    }
+
+    /// Width and height of the tiles the image is rendered in
+    pub fn tile_size(&mut self, tile_size: u32) -> &mut Self {
+        self.tile_size = Some(tile_size);
+        self
+    }
+
+    /// Order the tiles are rendered in. Starts in the center and spirals outwards by default.
+    pub fn tile_order(&mut self, tile_order: TileOrder) -> &mut Self {
+        self.tile_order = Some(tile_order);
+        self
+    }
+
+    /// Call `callback` with the progress of the render after every tile. It is called from the
+    /// rendering threads, so it should return quickly.
+    pub fn on_progress<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Save the render to `path` while it runs and when it is done, so `Camera::resume` can pick
+    /// it up again
+    pub fn checkpoint<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// Time between checkpoints. Defaults to a minute.
+    pub fn checkpoint_interval(&mut self, interval: Duration) -> &mut Self {
+        self.checkpoint_interval = Some(interval);
+        self
+    }
+
+    /// Collect the depth, normal, albedo, ids and the split light of every pixel while rendering,
+    /// see `Camera::aov_image`. Distributed renders only have the image.
+    pub fn aovs(&mut self, aovs: bool) -> &mut Self {
+        self.aovs = aovs;
+        self
+    }
+
+    /// Denoise the image at the end of every render, see `Camera::denoise`
+    pub fn denoiser(&mut self, denoiser: Denoiser) -> &mut Self {
+        self.denoiser = Some(denoiser);
+        self
+    }
+
+    /// Look at the default scene of `Scene::init` from above, like the command line does without
+    /// a scene file
+    pub fn default_scene_view(&mut self) -> &mut Self {
+        self.position_by_coordinates(Point::new(-6.0, 6.0, 15.0))
+            .look_at(Point::new(0.0, 0.0, 0.0))
+            .focal_length(2.0)
+    }
+
     pub fn position_by_coordinates(&mut self, position: Point) -> &mut Self {
         self.position = Some(position);
         self
@@ -203,4 +978,60 @@ impl CameraBuilder {
         self.sensor_width = Some(sensor_width);
         self
     }
+
+    /// Exposure in stops. Every stop doubles the brightness of the image.
+    pub fn exposure(&mut self, exposure: f64) -> &mut Self {
+        self.exposure = Some(exposure);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::small_camera;
+
+    /// Pixels of a small render of the default scene on `threads` threads
+    fn render(builder: &CameraBuilder, threads: usize) -> Pixels {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let mut camera = builder.build();
+        pool.install(|| camera.send_rays(Arc::new(Scene::init(0.5))));
+        camera.pixels
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_on_any_amount_of_threads() {
+        let builder = small_camera(8);
+        assert_eq!(render(&builder, 1), render(&builder, 4));
+    }
+
+    #[test]
+    fn adaptive_sampling_renders_the_same_on_any_amount_of_threads() {
+        let mut builder = small_camera(8);
+        builder.noise_threshold(0.1);
+        assert_eq!(render(&builder, 1), render(&builder, 4));
+    }
+
+    #[test]
+    fn second_render_starts_over() {
+        let builder = small_camera(8);
+        let mut camera = builder.build();
+        let scene = Arc::new(Scene::init(0.5));
+        camera.send_rays(scene.clone());
+        camera.send_rays(scene);
+
+        assert_eq!(camera.pixels, render(&builder, 2));
+        assert!(camera.sample_counts.iter().all(|samples| *samples == 8));
+    }
+
+    #[test]
+    fn other_seed_renders_differently() {
+        let mut builder = small_camera(8);
+        let first = render(&builder, 2);
+        builder.seed(8);
+        assert_ne!(first, render(&builder, 2));
+    }
 }
@@ -0,0 +1,268 @@
+use super::aov::AovEstimate;
+use super::camera::PixelEstimate;
+use super::Camera;
+use crate::type_aliases::{Color, Resolution};
+use crate::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Start of every checkpoint file, with the version of the format
+const MAGIC: &[u8; 8] = b"RTCKPT03";
+
+/// Longest sampler or integrator name a checkpoint is read with
+const MAX_NAME_LENGTH: u32 = 64;
+
+/// ### Checkpoint
+///
+/// Everything needed to continue a render: the sums of the samples and the AOVs of every pixel,
+/// and the seed.
+/// The random numbers of a sample only depend on the seed, the pixel and the index of the
+/// sample, so the seed stands in for the state of every generator. The names of the sampler and
+/// the integrator make sure the render continues with the same ones.
+///
+/// Stored as little-endian binary: the magic, the resolution, the seed, the names with their
+/// lengths in front, and then the running sums of every pixel. A byte tells whether the sums of
+/// the AOVs of every pixel follow, which they do when the render collected them.
+pub(crate) struct Checkpoint {
+    pub seed: u64,
+    pub resolution: Resolution,
+    pub sampler: String,
+    pub integrator: String,
+    pub estimates: Vec<PixelEstimate>,
+    /// Empty when the render did not collect the AOVs
+    pub aovs: Vec<AovEstimate>,
+}
+
+impl Checkpoint {
+    /// Write a checkpoint next to `path` and then move it over, so a crash while writing leaves
+    /// the previous checkpoint intact
+    pub fn write(path: &Path, camera: &Camera, estimates: &[PixelEstimate]) -> io::Result<()> {
+        let temporary = path.with_extension("tmp");
+        let mut file = BufWriter::new(File::create(&temporary)?);
+
+        file.write_all(MAGIC)?;
+        file.write_all(&camera.resolution.0.to_le_bytes())?;
+        file.write_all(&camera.resolution.1.to_le_bytes())?;
+        file.write_all(&camera.seed.to_le_bytes())?;
+        for name in [camera.sampler.name(), camera.integrator.name()] {
+            file.write_all(&(name.len() as u32).to_le_bytes())?;
+            file.write_all(name.as_bytes())?;
+        }
+        for estimate in estimates {
+            write_estimate(&mut file, estimate)?;
+        }
+        file.write_all(&[!camera.aov_estimates.is_empty() as u8])?;
+        for aov in &camera.aov_estimates {
+            aov.lock()
+                .expect("AOVs are not poisoned")
+                .write(&mut file)?;
+        }
+
+        file.into_inner()?.sync_all()?;
+        fs::rename(temporary, path)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::file(path))?;
+        let mut file = BufReader::new(file);
+
+        // Files shorter than the magic are not checkpoints either
+        match read_bytes::<8, _>(&mut file) {
+            Ok(magic) if &magic == MAGIC => {}
+            Err(error) if error.kind() != io::ErrorKind::UnexpectedEof => {
+                return Err(Error::file(path)(error));
+            }
+            _ => {
+                return Err(Error::Checkpoint(format!(
+                    "{} is not a checkpoint, or one from another version",
+                    path.display()
+                )));
+            }
+        }
+
+        Self::read_contents(&mut file).map_err(Error::file(path))
+    }
+
+    /// Everything after the magic
+    fn read_contents<R: Read>(file: &mut R) -> io::Result<Self> {
+        let resolution = (read_u32(file)?, read_u32(file)?);
+        let seed = u64::from_le_bytes(read_bytes(file)?);
+        let sampler = read_name(file)?;
+        let integrator = read_name(file)?;
+        let total_pixels = resolution.0 as usize * resolution.1 as usize;
+        let estimates = (0..total_pixels)
+            .map(|_| read_estimate(file))
+            .collect::<io::Result<_>>()?;
+        let [has_aovs] = read_bytes(file)?;
+        let aovs = match has_aovs {
+            0 => Vec::new(),
+            _ => (0..total_pixels)
+                .map(|_| AovEstimate::read(file))
+                .collect::<io::Result<_>>()?,
+        };
+
+        Ok(Self {
+            seed,
+            resolution,
+            sampler,
+            integrator,
+            estimates,
+            aovs,
+        })
+    }
+}
+
+/// Name of a sampler or an integrator, with its length in front
+fn read_name<R: Read>(reader: &mut R) -> io::Result<String> {
+    let length = read_u32(reader)?;
+    if length > MAX_NAME_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Name is too long",
+        ));
+    }
+
+    let mut name = vec![0; length as usize];
+    reader.read_exact(&mut name)?;
+    String::from_utf8(name).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Running sums of one pixel, in the byte order of checkpoints
+pub(crate) fn write_estimate<W: Write>(writer: &mut W, estimate: &PixelEstimate) -> io::Result<()> {
+    let total = estimate.total;
+    for value in [
+        total.x,
+        total.y,
+        total.z,
+        estimate.luminance,
+        estimate.luminance_squared,
+    ] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(&estimate.samples.to_le_bytes())
+}
+
+pub(crate) fn read_estimate<R: Read>(reader: &mut R) -> io::Result<PixelEstimate> {
+    let mut values = [0.0; 5];
+    for value in &mut values {
+        *value = f64::from_le_bytes(read_bytes(reader)?);
+    }
+    Ok(PixelEstimate {
+        total: Color::new(values[0], values[1], values[2]),
+        luminance: values[3],
+        luminance_squared: values[4],
+        samples: read_u32(reader)?,
+    })
+}
+
+pub(crate) fn read_bytes<const N: usize, R: Read>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub(crate) fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    read_bytes(reader).map(u32::from_le_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::{Aov, CameraBuilder, Scene};
+    use crate::samplers::Halton;
+    use crate::test_support::{small_camera, TempFile};
+    use std::sync::Arc;
+
+    fn render(builder: &CameraBuilder) -> Camera {
+        let mut camera = builder.build();
+        camera.send_rays(Arc::new(Scene::init(0.5)));
+        camera
+    }
+
+    #[test]
+    fn checkpoints_read_back_what_was_written() {
+        let file = TempFile::new("round-trip.ckpt");
+        let camera = render(&small_camera(4));
+        camera.save_checkpoint(&file.0).unwrap();
+
+        let checkpoint = Checkpoint::read(&file.0).unwrap();
+        assert_eq!(checkpoint.seed, camera.seed);
+        assert_eq!(checkpoint.resolution, camera.resolution);
+        assert_eq!(checkpoint.sampler, "sobol");
+        assert_eq!(checkpoint.integrator, "path");
+        assert_eq!(checkpoint.estimates, camera.estimates);
+    }
+
+    #[test]
+    fn resumed_renders_match_uninterrupted_ones() {
+        let file = TempFile::new("resume.ckpt");
+        render(small_camera(4).checkpoint(&file.0));
+
+        // The seed of the checkpoint replaces the one of the camera
+        let mut resumed = small_camera(8).seed(12).build();
+        resumed.resume(&file.0).unwrap();
+        resumed.send_rays(Arc::new(Scene::init(0.5)));
+        assert_eq!(resumed.pixels, render(&small_camera(8)).pixels);
+    }
+
+    #[test]
+    fn resumed_renders_keep_the_aovs_of_the_checkpoint() {
+        let file = TempFile::new("aovs.ckpt");
+        let finished = render(small_camera(4).aovs(true).checkpoint(&file.0));
+
+        // Nothing is left to render, so every AOV comes from the checkpoint
+        let mut resumed = small_camera(4).aovs(true).build();
+        resumed.resume(&file.0).unwrap();
+        resumed.send_rays(Arc::new(Scene::init(0.5)));
+        for aov in Aov::ALL {
+            assert_eq!(
+                resumed.aov_image(aov).unwrap().data,
+                finished.aov_image(aov).unwrap().data,
+                "{}",
+                aov.name()
+            );
+        }
+
+        // A checkpoint without AOVs can not give a render that collects them
+        render(&small_camera(4)).save_checkpoint(&file.0).unwrap();
+        let error = resumed.resume(&file.0).unwrap_err();
+        assert!(matches!(error, Error::Checkpoint(_)), "{error}");
+    }
+
+    #[test]
+    fn resuming_a_checkpoint_of_another_resolution_fails() {
+        let file = TempFile::new("resolution.ckpt");
+        render(&small_camera(2)).save_checkpoint(&file.0).unwrap();
+
+        let mut camera = small_camera(2).resolution(16, 24).build();
+        let error = camera.resume(&file.0).unwrap_err();
+        assert!(matches!(error, Error::Checkpoint(_)), "{error}");
+        assert_eq!(
+            error.to_string(),
+            "Invalid checkpoint: Checkpoint is 24x16, which does not match the camera"
+        );
+    }
+
+    #[test]
+    fn resuming_a_checkpoint_of_another_sampler_fails() {
+        let file = TempFile::new("sampler.ckpt");
+        render(&small_camera(2)).save_checkpoint(&file.0).unwrap();
+
+        let mut camera = small_camera(2).sampler(Arc::new(Halton)).build();
+        let error = camera.resume(&file.0).unwrap_err();
+        assert!(matches!(error, Error::Checkpoint(_)), "{error}");
+    }
+
+    #[test]
+    fn files_that_are_not_checkpoints_are_rejected() {
+        let file = TempFile::new("broken.ckpt");
+        fs::write(&file.0, b"P6\n12 8\n255\n").unwrap();
+        let error = Checkpoint::read(&file.0).err().unwrap();
+        assert!(matches!(error, Error::Checkpoint(_)), "{error}");
+
+        fs::write(&file.0, b"RTCKPT03 and then nothing useful").unwrap();
+        let error = Checkpoint::read(&file.0).err().unwrap();
+        assert!(matches!(error, Error::File { .. }), "{error}");
+    }
+}
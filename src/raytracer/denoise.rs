@@ -0,0 +1,292 @@
+use super::{Aov, Camera};
+use crate::config::denoise::*;
+use crate::type_aliases::{Color, Normal};
+use rayon::prelude::*;
+
+/// Weights of the 5x5 B3 spline kernel along one axis
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// ### Denoiser
+///
+/// Edge-avoiding à-trous wavelet filter. Every pass blurs the image with a 5x5 kernel whose taps
+/// are twice as far apart as in the pass before, so a few passes reach far. Neighbours only count
+/// as much as they look like the pixel: in their brightness, and in the normal, depth and albedo
+/// of the surface they show, which keeps edges and textures sharp while the noise is smoothed.
+///
+/// Differences in brightness are measured against the noise of the pixel, so converged pixels
+/// stay as they are and noisy ones are smoothed more. The sigmas set how different a neighbour
+/// can be before it is left out, larger ones blur more. Sigmas below `MIN_DENOISE_SIGMA`, and
+/// ones that are not a number, count as `MIN_DENOISE_SIGMA`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub passes: u32,
+    /// Difference in brightness, as a multiple of the standard error of the pixel
+    pub color_sigma: f64,
+    pub normal_sigma: f64,
+    /// Difference in depth, relative to the depth of the pixel
+    pub depth_sigma: f64,
+    pub albedo_sigma: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            passes: DEFAULT_DENOISE_PASSES,
+            color_sigma: DEFAULT_COLOR_SIGMA,
+            normal_sigma: DEFAULT_NORMAL_SIGMA,
+            depth_sigma: DEFAULT_DEPTH_SIGMA,
+            albedo_sigma: DEFAULT_ALBEDO_SIGMA,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn passes(mut self, passes: u32) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    pub fn color_sigma(mut self, sigma: f64) -> Self {
+        self.color_sigma = sigma;
+        self
+    }
+
+    pub fn normal_sigma(mut self, sigma: f64) -> Self {
+        self.normal_sigma = sigma;
+        self
+    }
+
+    pub fn depth_sigma(mut self, sigma: f64) -> Self {
+        self.depth_sigma = sigma;
+        self
+    }
+
+    pub fn albedo_sigma(mut self, sigma: f64) -> Self {
+        self.albedo_sigma = sigma;
+        self
+    }
+
+    /// Filter `pixels`, rows of `width` pixels with the 0-255 colors of `Camera::pixels`.
+    /// `variances` are the variances of the brightness of every pixel, and `guides` the surfaces
+    /// they show.
+    pub fn filter(
+        &self,
+        pixels: &[Color],
+        variances: &[f64],
+        guides: &[Guide],
+        width: usize,
+    ) -> Vec<Color> {
+        let denoiser = self.clamped();
+        let width = width.max(1);
+        let height = pixels.len() / width;
+        let mut pixels = pixels.to_vec();
+        let mut variances = variances.to_vec();
+
+        for pass in 0..denoiser.passes.min(usize::BITS - 1) {
+            let step = 1usize << pass;
+            let noise = blur(&variances, width, height);
+            let (image, variance) = (&pixels, &variances);
+            (pixels, variances) = (0..image.len())
+                .into_par_iter()
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let guide = &guides[index];
+                    let brightness = luminance(&image[index]);
+                    let sigma = denoiser.color_sigma * noise[index].sqrt() + MIN_DENOISE_NOISE;
+
+                    let mut total = Color::zeros();
+                    let mut total_variance = 0.0;
+                    let mut total_weight = 0.0;
+                    for (row, row_weight) in KERNEL.iter().enumerate() {
+                        let Some(qy) = (y + row * step).checked_sub(2 * step) else {
+                            continue;
+                        };
+                        for (column, column_weight) in KERNEL.iter().enumerate() {
+                            let Some(qx) = (x + column * step).checked_sub(2 * step) else {
+                                continue;
+                            };
+                            if qx >= width || qy >= height {
+                                continue;
+                            }
+
+                            let neighbour = qy * width + qx;
+                            let difference = (luminance(&image[neighbour]) - brightness).abs();
+                            let distance =
+                                difference / sigma + denoiser.distance(guide, &guides[neighbour]);
+                            let weight = row_weight * column_weight * (-distance).exp();
+                            total += image[neighbour] * weight;
+                            total_variance += variance[neighbour] * weight * weight;
+                            total_weight += weight;
+                        }
+                    }
+
+                    // The pixel itself always counts, so the weights never add up to 0
+                    (
+                        total / total_weight,
+                        total_variance / (total_weight * total_weight),
+                    )
+                })
+                .unzip();
+        }
+        pixels
+    }
+
+    /// The denoiser with every sigma at least `MIN_DENOISE_SIGMA`
+    fn clamped(&self) -> Self {
+        let clamp = |sigma: f64| sigma.max(MIN_DENOISE_SIGMA);
+        Self {
+            passes: self.passes,
+            color_sigma: clamp(self.color_sigma),
+            normal_sigma: clamp(self.normal_sigma),
+            depth_sigma: clamp(self.depth_sigma),
+            albedo_sigma: clamp(self.albedo_sigma),
+        }
+    }
+
+    /// How unlike the surfaces of two pixels are, in multiples of the sigmas
+    fn distance(&self, guide: &Guide, other: &Guide) -> f64 {
+        let normal = (guide.normal - other.normal).norm_squared() / self.normal_sigma.powi(2);
+        let depth = (guide.depth - other.depth).abs()
+            / (self.depth_sigma * guide.depth.max(MIN_DENOISE_DEPTH));
+        let albedo = (guide.albedo - other.albedo).norm_squared() / self.albedo_sigma.powi(2);
+        normal + depth + albedo
+    }
+}
+
+/// Surface a pixel shows, which the `Denoiser` keeps the edges of
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Guide {
+    pub normal: Normal,
+    pub depth: f64,
+    pub albedo: Color,
+}
+
+impl Camera {
+    /// Smooth the noise of `Camera::pixels` with `denoiser`, guided by the AOVs of the last
+    /// render. Does nothing when the render did not collect them. The samples are kept as they
+    /// are, so checkpoints hold the noisy image.
+    pub fn denoise(&mut self, denoiser: &Denoiser) {
+        let (Some(normals), Some(depths), Some(albedos)) = (
+            self.aov_image(Aov::Normal),
+            self.aov_image(Aov::Depth),
+            self.aov_image(Aov::Albedo),
+        ) else {
+            return;
+        };
+
+        let vector =
+            |values: &[f32]| Color::new(values[0] as f64, values[1] as f64, values[2] as f64);
+        let guides: Vec<Guide> = normals
+            .data
+            .chunks_exact(3)
+            .zip(depths.data.chunks_exact(3))
+            .zip(albedos.data.chunks_exact(3))
+            .map(|((normal, depth), albedo)| Guide {
+                normal: vector(normal),
+                depth: depth[0] as f64,
+                albedo: vector(albedo),
+            })
+            .collect();
+
+        // Pixels with a single sample count as very noisy
+        let variances: Vec<f64> = self
+            .estimates
+            .iter()
+            .map(|estimate| estimate.variance().min(MAX_DENOISE_VARIANCE))
+            .collect();
+
+        let (width, _) = self.resolution;
+        self.pixels = denoiser.filter(&self.pixels, &variances, &guides, width as usize);
+    }
+}
+
+/// Brightness of `color`, weighted like `PixelEstimate` does
+fn luminance(color: &Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// `values` smoothed with their 3x3 neighbourhood, so the noise of a pixel is not judged by its
+/// own few samples alone
+fn blur(values: &[f64], width: usize, height: usize) -> Vec<f64> {
+    const WEIGHTS: [f64; 3] = [0.25, 0.5, 0.25];
+    (0..values.len())
+        .into_par_iter()
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let mut total = 0.0;
+            let mut total_weight = 0.0;
+            for (row, row_weight) in WEIGHTS.iter().enumerate() {
+                for (column, column_weight) in WEIGHTS.iter().enumerate() {
+                    let (Some(qx), Some(qy)) =
+                        ((x + column).checked_sub(1), (y + row).checked_sub(1))
+                    else {
+                        continue;
+                    };
+                    if qx < width && qy < height {
+                        total += values[qy * width + qx] * row_weight * column_weight;
+                        total_weight += row_weight * column_weight;
+                    }
+                }
+            }
+            total / total_weight
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Guides of a 8x4 image that shows a wall facing the camera on the left half, and one
+    /// facing to the side on the right half
+    fn corner() -> Vec<Guide> {
+        (0..32)
+            .map(|index| Guide {
+                normal: match index % 8 < 4 {
+                    true => Normal::new(0.0, 0.0, 1.0),
+                    false => Normal::new(1.0, 0.0, 0.0),
+                },
+                depth: 5.0,
+                albedo: Color::repeat(0.5),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constant_images_stay_the_same() {
+        let pixels = vec![Color::new(100.0, 50.0, 25.0); 32];
+        let filtered = Denoiser::default().filter(&pixels, &[400.0; 32], &corner(), 8);
+        for pixel in filtered {
+            assert!((pixel - pixels[0]).norm() < 1e-9, "{pixel}");
+        }
+    }
+
+    #[test]
+    fn edges_between_surfaces_stay_sharp() {
+        let pixels: Vec<Color> = (0..32)
+            .map(|index| Color::repeat(if index % 8 < 4 { 0.0 } else { 200.0 }))
+            .collect();
+        // Noisy enough that the brightness alone would let the sides blend
+        let filtered = Denoiser::default().filter(&pixels, &[1e6; 32], &corner(), 8);
+        for (pixel, expected) in filtered.iter().zip(&pixels) {
+            assert!(
+                (pixel - expected).norm() < 1e-3,
+                "{pixel} is not {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn sigmas_of_zero_or_less_do_not_break_the_image() {
+        let pixels: Vec<Color> = (0..32).map(|index| Color::repeat(index as f64)).collect();
+        let denoiser = Denoiser::default()
+            .color_sigma(-1.0)
+            .normal_sigma(0.0)
+            .depth_sigma(-0.5)
+            .albedo_sigma(f64::NAN);
+        let filtered = denoiser.filter(&pixels, &[1.0; 32], &corner(), 8);
+        assert!(filtered
+            .iter()
+            .all(|pixel| pixel.iter().all(|c| c.is_finite())));
+    }
+}
@@ -0,0 +1,373 @@
+use super::camera::PixelEstimate;
+use super::checkpoint::{read_bytes, read_estimate, read_u32, write_estimate};
+use super::{parse_scene, Tile};
+use crate::Error;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::thread;
+
+/// Start of every job, with the version of the protocol
+const JOB_MAGIC: &[u8; 8] = b"RTJOB001";
+
+/// Longest scene file a worker accepts, so a job can not make it allocate any amount of memory
+const MAX_JOB_LENGTH: u32 = 256 << 20;
+
+/// Longest reason a worker may give for rejecting a job
+const MAX_MESSAGE_LENGTH: u32 = 64 << 10;
+
+/// Messages from the coordinator after the job
+const RENDER_TILE: u8 = 1;
+const JOB_DONE: u8 = 0;
+
+/// Answers of a worker to a job
+const JOB_ACCEPTED: u8 = 0;
+const JOB_REJECTED: u8 = 1;
+
+/// ### WorkerEvent
+///
+/// What `serve_worker` reports about the jobs of the coordinators
+#[derive(Debug)]
+pub enum WorkerEvent {
+    /// A coordinator connected to send a job
+    Started(SocketAddr),
+    /// The coordinator is done with the job, after the worker rendered `tiles` tiles of it
+    Finished {
+        coordinator: SocketAddr,
+        tiles: usize,
+    },
+    /// The job failed, and the worker waits for the next one
+    Failed {
+        coordinator: SocketAddr,
+        error: io::Error,
+    },
+}
+
+/// Render the jobs of coordinators that connect to `listener`, one job at a time, see
+/// `Camera::send_rays_distributed`. `on_event` hears about every job. Only returns when the
+/// listener fails.
+///
+/// A job starts with the scene file of the render. After that the coordinator sends tiles with
+/// the samples their pixels have so far, and the worker answers each with the finished pixels.
+/// Everything is little-endian, and pixels use the same layout as checkpoints.
+pub fn serve_worker<F: FnMut(WorkerEvent)>(
+    listener: TcpListener,
+    mut on_event: F,
+) -> Result<(), Error> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let coordinator = stream.peer_addr()?;
+        on_event(WorkerEvent::Started(coordinator));
+        on_event(match render_job(stream) {
+            Ok(tiles) => WorkerEvent::Finished { coordinator, tiles },
+            Err(error) => WorkerEvent::Failed { coordinator, error },
+        });
+    }
+    Ok(())
+}
+
+/// Render the tiles of one job until the coordinator is done, and count them
+fn render_job(stream: TcpStream) -> io::Result<usize> {
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    if &read_bytes::<8, _>(&mut reader)? != JOB_MAGIC {
+        return Err(invalid_data(
+            "Not a render job, or one from another version",
+        ));
+    }
+    let job = read_text(&mut reader, MAX_JOB_LENGTH)?;
+
+    let (scene, camera) = match parse_scene(&job) {
+        Ok((scene, builder)) => (scene, builder.build()),
+        Err(error) => {
+            let message = error.to_string();
+            writer.write_all(&[JOB_REJECTED])?;
+            write_text(&mut writer, &message)?;
+            writer.flush()?;
+            return Err(invalid_data(&message));
+        }
+    };
+    writer.write_all(&[JOB_ACCEPTED])?;
+    writer.flush()?;
+
+    camera.integrator.prepare(&scene, camera.seed);
+    let (width, height) = camera.resolution;
+    let mut tiles = 0;
+    loop {
+        let [message] = read_bytes(&mut reader)?;
+        if message == JOB_DONE {
+            return Ok(tiles);
+        }
+
+        let tile = read_tile(&mut reader)?;
+        let inside = tile.x.saturating_add(tile.width) <= width
+            && tile.y.saturating_add(tile.height) <= height;
+        if !inside {
+            return Err(invalid_data("Tile is outside of the image"));
+        }
+        let mut estimates = (0..tile.width * tile.height)
+            .map(|_| read_estimate(&mut reader))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        camera.sample_tile(&scene, &tile, &mut estimates);
+        for estimate in &estimates {
+            write_estimate(&mut writer, estimate)?;
+        }
+        writer.flush()?;
+        tiles += 1;
+    }
+}
+
+/// Hand `tiles` to the workers until every tile is rendered. `on_tile` is called after each one.
+/// Workers that fail are left out, and their errors go to `failures`.
+pub(crate) fn render_tiles<A: ToSocketAddrs>(
+    workers: &[A],
+    job: &str,
+    width: u32,
+    tiles: &[Tile],
+    estimates: &Mutex<Vec<PixelEstimate>>,
+    on_tile: &(dyn Fn() + Sync),
+    failures: &Mutex<Vec<Error>>,
+) -> io::Result<()> {
+    let fail = |index, source| {
+        let mut failures = failures.lock().expect("Failures are not poisoned");
+        failures.push(Error::Worker { index, source });
+    };
+    let mut connections: Vec<Connection> = workers
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(index, worker)| match Connection::open(index, worker, job) {
+                Ok(connection) => Some(connection),
+                Err(error) => {
+                    fail(index, error);
+                    None
+                }
+            },
+        )
+        .collect();
+
+    // Tiles are taken from the back, so the first tile goes first
+    let queue = &Mutex::new(tiles.iter().rev().copied().collect::<Vec<_>>());
+    let next_tile = || queue.lock().expect("Queue is not poisoned").pop();
+
+    // Tiles of workers that fail are put back, and go to the workers that are left in the next
+    // round
+    while !queue.lock().expect("Queue is not poisoned").is_empty() {
+        if connections.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "No worker is left to render the image",
+            ));
+        }
+
+        connections = thread::scope(|scope| {
+            let workers: Vec<_> = connections
+                .into_iter()
+                .map(|mut connection| {
+                    let fail = &fail;
+                    scope.spawn(move || {
+                        while let Some(tile) = next_tile() {
+                            if let Err(error) = connection.render(&tile, width, estimates) {
+                                fail(connection.index, error);
+                                queue.lock().expect("Queue is not poisoned").push(tile);
+                                return None;
+                            }
+                            on_tile();
+                        }
+                        Some(connection)
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().expect("Worker thread does not panic"))
+                .collect()
+        });
+    }
+
+    for mut connection in connections {
+        connection.finish();
+    }
+    Ok(())
+}
+
+/// Coordinator side of the connection to one worker
+struct Connection {
+    /// Position of the worker in the list of the render
+    index: usize,
+    address: SocketAddr,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Connection {
+    /// Connect to `worker` and hand it the job
+    fn open<A: ToSocketAddrs>(index: usize, worker: A, job: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(worker)?;
+        stream.set_nodelay(true)?;
+        let mut connection = Self {
+            index,
+            address: stream.peer_addr()?,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        };
+
+        if job.len() > MAX_JOB_LENGTH as usize {
+            return Err(invalid_data("Scene file is too long to send to a worker"));
+        }
+        connection.writer.write_all(JOB_MAGIC)?;
+        write_text(&mut connection.writer, job)?;
+        connection.writer.flush()?;
+
+        let [answer] = read_bytes(&mut connection.reader)?;
+        if answer != JOB_ACCEPTED {
+            let message = read_text(&mut connection.reader, MAX_MESSAGE_LENGTH)?;
+            return Err(invalid_data(&format!(
+                "{} rejected the scene: {message}",
+                connection.address
+            )));
+        }
+        Ok(connection)
+    }
+
+    /// Send `tile` with the samples its pixels have so far, and store the pixels that come back
+    fn render(
+        &mut self,
+        tile: &Tile,
+        width: u32,
+        estimates: &Mutex<Vec<PixelEstimate>>,
+    ) -> io::Result<()> {
+        let pixels: Vec<usize> = tile.pixels(width).collect();
+
+        self.writer.write_all(&[RENDER_TILE])?;
+        for value in [tile.x, tile.y, tile.width, tile.height] {
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        {
+            let estimates = estimates.lock().expect("Estimates are not poisoned");
+            for &pixel in &pixels {
+                write_estimate(&mut self.writer, &estimates[pixel])?;
+            }
+        }
+        self.writer.flush()?;
+
+        let rendered = pixels
+            .iter()
+            .map(|_| read_estimate(&mut self.reader))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut estimates = estimates.lock().expect("Estimates are not poisoned");
+        for (pixel, estimate) in pixels.into_iter().zip(rendered) {
+            estimates[pixel] = estimate;
+        }
+        Ok(())
+    }
+
+    /// Let the worker know the job is done. The worker finds out anyway when the connection
+    /// closes, so errors don't matter.
+    fn finish(&mut self) {
+        self.writer.write_all(&[JOB_DONE]).ok();
+        self.writer.flush().ok();
+    }
+}
+
+fn read_tile<R: Read>(reader: &mut R) -> io::Result<Tile> {
+    Ok(Tile {
+        x: read_u32(reader)?,
+        y: read_u32(reader)?,
+        width: read_u32(reader)?,
+        height: read_u32(reader)?,
+    })
+}
+
+/// Text with its length in front
+fn write_text<W: Write>(writer: &mut W, text: &str) -> io::Result<()> {
+    writer.write_all(&(text.len() as u32).to_le_bytes())?;
+    writer.write_all(text.as_bytes())
+}
+
+/// Text with its length in front, refused when it is longer than `max_length` bytes
+fn read_text<R: Read>(reader: &mut R, max_length: u32) -> io::Result<String> {
+    let length = read_u32(reader)?;
+    if length > max_length {
+        return Err(invalid_data(&format!(
+            "Text of {length} bytes is longer than the {max_length} allowed"
+        )));
+    }
+
+    let mut text = vec![0; length as usize];
+    reader.read_exact(&mut text)?;
+    String::from_utf8(text).map_err(|_| invalid_data("Text is not UTF-8"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::Scene;
+    use crate::test_support::small_camera;
+    use std::sync::Arc;
+
+    #[test]
+    fn workers_render_the_same_image_as_one_machine() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // The worker never returns, and is stopped with the tests
+        thread::spawn(move || serve_worker(listener, |_| {}));
+
+        let scene = Arc::new(Scene::init(0.5));
+        let mut builder = small_camera(4);
+        builder.tile_size(8);
+        let mut distributed = builder.build();
+        distributed
+            .send_rays_distributed(scene.clone(), &[address])
+            .unwrap();
+        assert!(distributed.worker_errors.is_empty());
+
+        let mut local = builder.build();
+        local.send_rays(scene);
+        assert_eq!(distributed.pixels, local.pixels);
+    }
+
+    #[test]
+    fn workers_that_can_not_be_reached_are_reported() {
+        // Bound and dropped again, so nothing listens on the address
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut camera = small_camera(1).build();
+        let rendered = camera.send_rays_distributed(Arc::new(Scene::init(0.5)), &[address]);
+
+        assert!(rendered.is_err());
+        assert!(matches!(
+            camera.worker_errors.as_slice(),
+            [Error::Worker { index: 0, .. }]
+        ));
+    }
+
+    #[test]
+    fn texts_read_back_what_was_written() {
+        let mut bytes = Vec::new();
+        write_text(&mut bytes, "resolution 4 3").unwrap();
+        assert_eq!(
+            read_text(&mut bytes.as_slice(), MAX_JOB_LENGTH).unwrap(),
+            "resolution 4 3"
+        );
+    }
+
+    #[test]
+    fn texts_longer_than_allowed_are_refused_before_reading_them() {
+        // Only the length is there, so reading any further would fail differently
+        let bytes = (MAX_JOB_LENGTH + 1).to_le_bytes();
+        let error = read_text(&mut bytes.as_slice(), MAX_JOB_LENGTH).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,330 @@
+use super::Camera;
+use crate::Error;
+use std::path::Path;
+
+/// Gamma the 8-bit conversions encode with, the same as `Camera::write_to_ppm`
+const DISPLAY_GAMMA: f32 = 2.0;
+
+/// Channels of every pixel of an `Image`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    #[default]
+    Rgb,
+    /// Rgb with an alpha channel, which is always 1.0 since every camera ray finds some light
+    Rgba,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::Rgb => 3,
+            PixelFormat::Rgba => 4,
+        }
+    }
+}
+
+/// File formats `Image::encode` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    /// Binary PPM, 8 bits per channel
+    #[default]
+    Ppm,
+    /// Uncompressed PNG, 8 bits per channel with alpha for RGBA images
+    Png,
+    /// Portable float map with the linear light of every pixel, for HDR
+    Pfm,
+}
+
+/// Names accepted by `ImageFormat::from_name`, which are also the file extensions
+pub const IMAGE_FORMATS: [&str; 3] = ["ppm", "png", "pfm"];
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Format that goes with the extension of `path`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        Self::from_name(path.as_ref().extension()?.to_str()?)
+    }
+}
+
+/// ### Image
+///
+/// Rendered image in memory. `data` holds linear light with 1.0 as white, row by row from the top
+/// left, with the channels of `format` for every pixel. Bright pixels can go above 1.0 until they
+/// are converted to 8 bits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: Vec<f32>,
+}
+
+impl Image {
+    /// Black image
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        let mut data = vec![0.0; width as usize * height as usize * format.channels()];
+        if format == PixelFormat::Rgba {
+            data.iter_mut()
+                .skip(3)
+                .step_by(4)
+                .for_each(|alpha| *alpha = 1.0);
+        }
+
+        Self {
+            width,
+            height,
+            format,
+            data,
+        }
+    }
+
+    /// Pixels of the last render of `camera`, with its exposure applied
+    pub fn from_camera(camera: &Camera, format: PixelFormat) -> Self {
+        let (width, height) = camera.resolution;
+        let mut image = Self::new(width, height, format);
+        let scale = 2f64.powf(camera.exposure) / 255.0;
+
+        let pixels = image.data.chunks_exact_mut(format.channels());
+        for (pixel, color) in pixels.zip(&camera.pixels) {
+            pixel[0] = (color.x * scale) as f32;
+            pixel[1] = (color.y * scale) as f32;
+            pixel[2] = (color.z * scale) as f32;
+        }
+        image
+    }
+
+    /// Channels of the pixel in column `x` and row `y`, counted from the top left
+    pub fn pixel(&self, x: u32, y: u32) -> &[f32] {
+        let channels = self.format.channels();
+        let start = (y as usize * self.width as usize + x as usize) * channels;
+        &self.data[start..start + channels]
+    }
+
+    /// 8-bit RGB, gamma encoded for display and clamped to 0-255
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(self.format.channels())
+            .flat_map(|pixel| pixel[..3].iter().map(|&value| encode(value)))
+            .collect()
+    }
+
+    /// 8-bit RGBA, like `to_rgb8` with a linear alpha channel
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(self.format.channels())
+            .flat_map(|pixel| {
+                let alpha = pixel.get(3).copied().unwrap_or(1.0);
+                let alpha = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+                [encode(pixel[0]), encode(pixel[1]), encode(pixel[2]), alpha]
+            })
+            .collect()
+    }
+
+    /// The image as the bytes of a binary PPM file
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.to_rgb8());
+        bytes
+    }
+
+    /// The image as the bytes of a PNG file. The pixels are stored without compression.
+    pub fn to_png(&self) -> Vec<u8> {
+        let (pixels, color_type, channels) = match self.format {
+            PixelFormat::Rgb => (self.to_rgb8(), 2, 3),
+            PixelFormat::Rgba => (self.to_rgba8(), 6, 4),
+        };
+
+        // Every row starts with the filter it uses, which is none
+        let row_length = self.width as usize * channels;
+        let mut rows = Vec::with_capacity((row_length + 1) * self.height as usize);
+        for row in pixels.chunks(row_length.max(1)) {
+            rows.push(0);
+            rows.extend_from_slice(row);
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        header.extend([8, color_type, 0, 0, 0]);
+
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut bytes, b"IHDR", &header);
+        png_chunk(&mut bytes, b"IDAT", &zlib_stored(&rows));
+        png_chunk(&mut bytes, b"IEND", &[]);
+        bytes
+    }
+
+    /// The image as the bytes of a PFM file, which keeps the linear light of every pixel
+    pub fn to_pfm(&self) -> Vec<u8> {
+        let mut bytes = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+
+        // Rows go from the bottom up
+        let row_length = self.width as usize * self.format.channels();
+        for row in self.data.chunks(row_length.max(1)).rev() {
+            for pixel in row.chunks_exact(self.format.channels()) {
+                pixel[..3]
+                    .iter()
+                    .for_each(|value| bytes.extend(value.to_le_bytes()));
+            }
+        }
+        bytes
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Ppm => self.to_ppm(),
+            ImageFormat::Png => self.to_png(),
+            ImageFormat::Pfm => self.to_pfm(),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> Result<(), Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.encode(format)).map_err(Error::file(path))
+    }
+}
+
+/// Append a chunk of a PNG file, with its length and checksum
+fn png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend((data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend(crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Zlib stream that stores `data` in uncompressed blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = u16::MAX as usize;
+
+    let mut bytes = vec![0x78, 0x01];
+    let blocks = data.len().div_ceil(BLOCK_SIZE).max(1);
+    for index in 0..blocks {
+        let block = &data[index * BLOCK_SIZE..((index + 1) * BLOCK_SIZE).min(data.len())];
+        let length = block.len() as u16;
+        bytes.push((index + 1 == blocks) as u8);
+        bytes.extend(length.to_le_bytes());
+        bytes.extend((!length).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+
+    bytes.extend(adler32(data).to_be_bytes());
+    bytes
+}
+
+/// Checksum at the end of a zlib stream
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn encode(value: f32) -> u8 {
+    (value.max(0.0).powf(1.0 / DISPLAY_GAMMA) * 255.0).min(255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::CameraBuilder;
+    use crate::type_aliases::Color;
+
+    #[test]
+    fn camera_images_are_linear_and_scaled_by_the_exposure() {
+        let mut camera = CameraBuilder::new().resolution(2, 1).exposure(1.0).build();
+        camera.pixels = vec![Color::new(255.0, 127.5, 0.0), Color::new(51.0, 0.0, 510.0)];
+
+        let image = Image::from_camera(&camera, PixelFormat::Rgb);
+        assert_eq!(image.data, [2.0, 1.0, 0.0, 0.4, 0.0, 4.0]);
+        let image = Image::from_camera(&camera, PixelFormat::Rgba);
+        assert_eq!(image.pixel(1, 0), [0.4, 0.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn bytes_are_gamma_encoded_and_clamped() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            format: PixelFormat::Rgba,
+            data: vec![0.0, 0.25, 1.0, 0.5, -1.0, 4.0, f32::NAN, 2.0],
+        };
+        assert_eq!(image.to_rgb8(), [0, 127, 255, 0, 255, 0]);
+        assert_eq!(image.to_rgba8(), [0, 127, 255, 128, 0, 255, 0, 255]);
+
+        // Images without alpha are opaque
+        let image = Image::new(1, 1, PixelFormat::Rgb);
+        assert_eq!(image.to_rgba8(), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn zlib_streams_hold_the_data_in_stored_blocks() {
+        let data: Vec<u8> = (0..150_000).map(|index| (index % 251) as u8).collect();
+        let stream = zlib_stored(&data);
+        assert_eq!(stream[..2], [0x78, 0x01]);
+
+        // Every block is a final flag, the length and its complement, and the data
+        let mut stored = Vec::new();
+        let mut rest = &stream[2..stream.len() - 4];
+        let mut finals = Vec::new();
+        while !rest.is_empty() {
+            let length = u16::from_le_bytes([rest[1], rest[2]]);
+            assert_eq!(!length, u16::from_le_bytes([rest[3], rest[4]]));
+            finals.push(rest[0]);
+            stored.extend_from_slice(&rest[5..5 + length as usize]);
+            rest = &rest[5 + length as usize..];
+        }
+        assert_eq!(stored, data);
+        assert_eq!(finals, [0, 0, 1]);
+        assert_eq!(stream[stream.len() - 4..], adler32(&data).to_be_bytes());
+    }
+
+    #[test]
+    fn png_chunks_end_with_their_checksum() {
+        let png = Image::new(3, 2, PixelFormat::Rgba).to_png();
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+
+        let mut rest = &png[8..];
+        let mut kinds = Vec::new();
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let chunk = &rest[4..8 + length];
+            let crc = u32::from_be_bytes(rest[8 + length..12 + length].try_into().unwrap());
+            assert_eq!(crc32(chunk), crc);
+            kinds.push(String::from_utf8_lossy(&chunk[..4]).into_owned());
+            rest = &rest[12 + length..];
+        }
+        assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+    }
+}
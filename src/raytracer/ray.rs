@@ -1,123 +1,284 @@
-use crate::type_aliases::Directions;
-use crate::{config::rays::*, textures::Texture, type_aliases::Color};
+use super::statistics;
+use crate::objects::Object;
+use crate::textures::{power_heuristic, Texture};
+use crate::{config::rays::*, type_aliases::Color};
 
 #[derive(Debug, Clone)]
 pub struct Ray {
     pub origin: Point,
     pub direction: Direction,
-    pub collisions: Vec<Color>,
-    pub hit_light_source: bool,
+    pub radiance: Color,
     pub intersection_dist: f64,
-    pub depth: u8,
+    pub depth: u32,
+    pub max_depth: u32,
+    /// Product of the BSDF weights along the path so far. Light found at the current vertex is
+    /// scaled by it before being added to `radiance`.
+    pub throughput: Color,
+    /// Density of the BSDF sample this ray continues. `None` for camera rays and mirrors, which
+    /// the lights could never have been sampled against.
+    pub bsdf_pdf: Option<f64>,
+    /// Parts of `radiance` the camera sees straight from a light or the background, and that
+    /// light the first surface receives straight from a light or the background
+    pub emitted: Color,
+    pub direct: Color,
+    /// First surface the ray hit while its depth was 0, for the AOVs
+    pub first_hit: Option<FirstHit>,
+}
+
+/// What `Ray::closest_intersection` found for a camera ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FirstHit {
+    pub distance: f64,
+    pub normal: Normal,
+    pub texture: Texture,
+    /// Index of the object in `Scene::objects`
+    pub object: usize,
 }
 
 impl Ray {
-    pub fn new(origin: Point, direction: Point, depth: u8) -> Self {
+    pub fn new(origin: Point, direction: Point, depth: u32) -> Self {
         Self {
             origin,
             direction: direction.normalize(),
-            collisions: Vec::new(),
-            hit_light_source: false,
+            radiance: Color::zeros(),
             intersection_dist: f64::MAX,
             depth,
+            max_depth: DEFAULT_MAX_DEPTH,
+            throughput: Color::repeat(1.0),
+            bsdf_pdf: None,
+            emitted: Color::zeros(),
+            direct: Color::zeros(),
+            first_hit: None,
         }
     }
 
-    /// Find the closest intersection, create a new ray based on the surface, and recursively call
-    /// `trace`
-    pub fn trace(&mut self, scene: &Scene) {
-        if self.reached_max_depth() {
-            return;
+    /// Add light arriving at the current vertex, scaled by everything absorbed on the way back
+    pub fn add_radiance(&mut self, light: Color) {
+        let light = self.throughput.component_mul(&light);
+        self.radiance += light;
+        if self.depth == 0 {
+            self.direct += light;
         }
+    }
 
-        // Process the closest intersection
-        if let Some(intersection) = self.closest_intersection(scene) {
-            let small_offset = 1e-3 * intersection.normal;
-            let origin = intersection.hit_point + small_offset;
-            let normal = intersection.normal;
-
-            // Reflect based on object texture
-            match intersection.texture {
-                Texture::Diffusive(color) => {
-                    self.collisions.push(color);
-                    let direction = self.diffuse_direction(normal);
-                    if direction.near_zero() {
-                        self.reflect(origin, normal, scene);
-                    } else {
-                        self.reflect(origin, direction, scene);
-                    }
-                }
-                Texture::Reflective => {
-                    let direction = self.perfect_reflection(normal);
-                    self.reflect(origin, direction, scene);
-                }
+    /// Add light given off by the light or the background the ray reached, like `add_radiance`
+    pub fn add_emission(&mut self, light: Color) {
+        let light = self.throughput.component_mul(&light);
+        self.radiance += light;
+        match self.depth {
+            0 => self.emitted += light,
+            1 => self.direct += light,
+            _ => {}
+        }
+    }
 
-                Texture::Light(color) => {
-                    self.collisions.push(color);
-                    self.hit_light_source = true;
-                }
+    /// Move the ray to the next vertex of the path, with `weight` absorbed by the scattering.
+    /// Returns `false` when the path is terminated.
+    ///
+    /// From `ROULETTE_DEPTH` on, paths carrying little light are terminated at random. Surviving
+    /// paths are scaled up to make up for the terminated ones, which keeps the image unbiased.
+    pub fn bounce<R: Rng>(
+        &mut self,
+        origin: Point,
+        direction: Direction,
+        bsdf_pdf: Option<f64>,
+        weight: Color,
+        rng: &mut R,
+    ) -> bool {
+        self.throughput = self.throughput.component_mul(&weight);
+        self.depth += 1;
+
+        if self.depth >= ROULETTE_DEPTH {
+            let survival = self.throughput.max().min(0.95);
+            if rng.gen::<f64>() >= survival {
+                return false;
             }
+            self.throughput /= survival;
+        }
+
+        self.origin = origin;
+        self.direction = direction.normalize();
+        self.bsdf_pdf = bsdf_pdf;
+        self.intersection_dist = f64::MAX;
+        true
+    }
+
+    /// ### direct_light
+    ///
+    /// Sample a point on a random light and return the light it sends towards `point`.
+    ///
+    /// `scattering` returns the share of light arriving from a direction that scatters along the
+    /// path (BSDF and cosine for surfaces, phase function for media), and the density of sampling
+    /// that direction with the BSDF. The two strategies are combined with the power heuristic.
+    pub fn direct_light<F, R>(
+        &self,
+        scene: &Scene,
+        point: Point,
+        scattering: F,
+        rng: &mut R,
+    ) -> Color
+    where
+        F: Fn(Direction) -> (Color, f64),
+        R: Rng,
+    {
+        let light_count = scene.lights().count();
+        if light_count == 0 {
+            return Color::zeros();
+        }
+
+        let light = scene
+            .lights()
+            .nth(rng.gen_range(0..light_count))
+            .expect("Light index is in range");
+        let Some((light_point, light_normal)) = light.sample_surface(rng) else {
+            return Color::zeros();
+        };
+
+        let to_light = light_point - point;
+        let distance = to_light.norm();
+        let wi = to_light / distance;
+        let cos_light = light_normal.dot(&wi).abs();
+        if cos_light <= 1e-6 {
+            return Color::zeros();
+        }
+
+        let (factor, bsdf_pdf) = scattering(wi);
+        if factor.max() <= 0.0 {
+            return Color::zeros();
+        }
+
+        let light_pdf = distance * distance / (cos_light * light.area() * light_count as f64);
+        let emission = light.texture().emission().unwrap_or_default();
+        let visibility = self.visibility(scene, point, wi, distance * (1.0 - 1e-4), rng);
+
+        factor.component_mul(&emission) * visibility * power_heuristic(light_pdf, bsdf_pdf)
+            / light_pdf
+    }
+
+    /// MIS weight for emission found by following the BSDF, against sampling the light directly
+    pub fn emission_weight(
+        &self,
+        scene: &Scene,
+        light: &dyn Object,
+        intersection: &Intersection,
+    ) -> f64 {
+        let Some(bsdf_pdf) = self.bsdf_pdf else {
+            return 1.0;
+        };
+
+        let cos_light = intersection.normal.dot(&self.direction).abs();
+        if light.area() <= 0.0 || cos_light <= 1e-6 {
+            return 1.0;
         }
+
+        let distance = intersection.distance;
+        let light_pdf =
+            distance * distance / (cos_light * light.area() * scene.lights().count() as f64);
+        power_heuristic(bsdf_pdf, light_pdf)
+    }
+
+    /// Fraction of light that travels unblocked from `origin` to `distance` along `direction`
+    pub fn visibility<R: Rng>(
+        &self,
+        scene: &Scene,
+        origin: Point,
+        direction: Direction,
+        distance: f64,
+        rng: &mut R,
+    ) -> f64 {
+        if self.occluded(scene, origin, direction, distance) {
+            return 0.0;
+        }
+
+        let mut shadow_ray = Ray::new(origin, direction, self.depth + 1);
+        shadow_ray.intersection_dist = distance;
+        let fog = scene.fog.map_or(1.0, |fog| (-fog.density * distance).exp());
+
+        scene.volumes.iter().fold(fog, |transmittance, volume| {
+            transmittance * volume.transmittance(&shadow_ray, distance, rng)
+        })
+    }
+
+    /// Whether any object blocks the way from `origin` to `distance` along `direction`
+    pub fn occluded(
+        &self,
+        scene: &Scene,
+        origin: Point,
+        direction: Direction,
+        distance: f64,
+    ) -> bool {
+        let mut shadow_ray = Ray::new(origin, direction, self.depth + 1);
+        shadow_ray.intersection_dist = distance;
+
+        let blocker = scene.objects.iter().position(|object| {
+            object
+                .intersection(&shadow_ray)
+                .is_some_and(|intersection| intersection.distance < distance)
+        });
+        // Objects after the blocker are not tested
+        let tested = blocker.map_or(scene.objects.len(), |index| index + 1);
+        statistics::count_shadow_ray(&scene.objects[..tested]);
+        blocker.is_some()
     }
 
-    fn closest_intersection(&mut self, scene: &Scene) -> Option<Intersection> {
-        let mut closest_intersection: Option<Intersection> = None;
-        for object in &scene.objects {
+    pub fn closest_intersection<'a>(
+        &mut self,
+        scene: &'a Scene,
+    ) -> Option<(Intersection, &'a Arc<dyn Object>)> {
+        statistics::count_ray(&scene.objects);
+        let mut closest_intersection = None;
+        let mut closest_index = 0;
+        for (index, object) in scene.objects.iter().enumerate() {
             if let Some(intersection) = object.intersection(self) {
                 if intersection.distance < self.intersection_dist {
                     self.intersection_dist = intersection.distance;
-                    closest_intersection = Some(intersection);
+                    closest_intersection = Some((intersection, object));
+                    closest_index = index;
                 }
             }
         }
-        closest_intersection
-    }
-
-    /// ### diffuse_direction
-    ///
-    /// Generate a random direction for diffuse reflection on a hemisphere given a surface normal
-    fn diffuse_direction(&self, normal: Normal) -> Direction {
-        let mut rng = rand::thread_rng();
-
-        // Create a local coordinate system around the normal
-        let incident_ray = normal.normalize();
-        let tangent_a = if incident_ray.x.abs() > 0.9 {
-            Vector3::new(0.0, -1.0, 0.0)
-        } else {
-            Vector3::new(-1.0, 0.0, 0.0)
-        };
-        let tangent_v = incident_ray.cross(&tangent_a).normalize();
-        let tangent_u = incident_ray.cross(&tangent_v);
-
-        // Generate random points on a hemisphere
-        let rand_1: f64 = rng.gen();
-        let rand_2: f64 = rng.gen();
-        let sin_theta = (1.0 - rand_2 * rand_2).sqrt();
-        let phi = 2.0 * std::f64::consts::PI * rand_1;
-        let local_x = phi.cos() * sin_theta;
-        let local_y = phi.sin() * sin_theta;
-        let local_z = rand_2.sqrt();
-
-        // Convert to world coordinates
-        tangent_u * local_x + tangent_v * local_y + incident_ray * local_z
-    }
 
-    fn perfect_reflection(&self, normal: Normal) -> Direction {
-        self.direction - 2.0 * self.direction.dot(&normal) * normal
+        if self.depth == 0 && self.first_hit.is_none() {
+            self.first_hit = closest_intersection
+                .as_ref()
+                .map(|(intersection, _)| FirstHit {
+                    distance: intersection.distance,
+                    normal: intersection.normal,
+                    texture: intersection.texture,
+                    object: closest_index,
+                });
+        }
+        closest_intersection
     }
-    pub fn reflect(&mut self, origin: Point, direction: Direction, scene: &Scene) {
-        let mut secondary_ray = Ray::new(origin, direction, self.depth + 1);
 
-        secondary_ray.trace(scene);
+    /// Sample a free-flight distance through the global fog and every volume on the way to the
+    /// closest surface. Returns the distance to the first particle the ray collides with.
+    pub fn sample_media<R: Rng + ?Sized>(
+        &mut self,
+        scene: &Scene,
+        rng: &mut R,
+    ) -> Option<(f64, Medium)> {
+        let mut scatter = None;
 
-        self.collisions.extend(secondary_ray.collisions);
+        if let Some(fog) = scene.fog {
+            let distance = fog.sample_distance(rng);
+            if distance < self.intersection_dist {
+                self.intersection_dist = distance;
+                scatter = Some((distance, fog));
+            }
+        }
 
-        if secondary_ray.hit_light_source {
-            self.hit_light_source = true;
+        for volume in &scene.volumes {
+            if let Some(distance) = volume.sample_distance(self, self.intersection_dist, rng) {
+                self.intersection_dist = distance;
+                scatter = Some((distance, volume.medium));
+            }
         }
+
+        scatter
     }
 
-    fn reached_max_depth(&self) -> bool {
-        self.depth >= MAX_DEPTH
+    pub fn reached_max_depth(&self) -> bool {
+        self.depth >= self.max_depth
     }
 }
@@ -0,0 +1,111 @@
+use super::{Camera, Image, PixelFormat, Progressive, RenderEnd, Scene};
+use crate::Error;
+use std::io;
+use std::sync::Arc;
+
+/// ### RenderSettings
+///
+/// How `render` runs, apart from what the camera already decides. By default it renders RGB on
+/// every thread of the global rayon pool, until every pixel has its samples.
+#[derive(Debug, Clone, Default)]
+pub struct RenderSettings {
+    pub format: PixelFormat,
+    /// Threads that trace rays, `None` shares the global rayon pool
+    pub threads: Option<usize>,
+    /// Render in passes that can stop early at a time budget or when cancelled
+    pub progressive: Option<Progressive>,
+}
+
+impl RenderSettings {
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    pub fn progressive(mut self, progressive: Progressive) -> Self {
+        self.progressive = Some(progressive);
+        self
+    }
+}
+
+/// What `render` hands back
+#[derive(Debug, Clone)]
+pub struct RenderOutput {
+    pub image: Image,
+    /// Why the render stopped, always `RenderEnd::Finished` unless it was progressive
+    pub end: RenderEnd,
+}
+
+/// Render `scene` with `camera` and hand back the image, without touching the filesystem unless
+/// the camera saves checkpoints. The camera keeps its pixels as well, and checkpoints that could
+/// not be saved in `Camera::checkpoint_error`. Fails when the threads can not be started.
+pub fn render(
+    scene: Arc<Scene>,
+    camera: &mut Camera,
+    settings: &RenderSettings,
+) -> Result<RenderOutput, Error> {
+    let mut send_rays = || match &settings.progressive {
+        Some(progressive) => camera.send_rays_progressive(scene.clone(), progressive, |_, _| {}),
+        None => {
+            camera.send_rays(scene.clone());
+            RenderEnd::Finished
+        }
+    };
+
+    // Without a pool of its own the render runs on the global one
+    let end = match settings.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(io::Error::other)?
+            .install(send_rays),
+        None => send_rays(),
+    };
+
+    Ok(RenderOutput {
+        image: Image::from_camera(camera, settings.format),
+        end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::small_camera;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn rendered_images_match_the_pixels_of_the_camera() {
+        let scene = Arc::new(Scene::init(0.5));
+        let mut camera = small_camera(2).build();
+        let settings = RenderSettings::default()
+            .format(PixelFormat::Rgba)
+            .threads(2);
+        let output = render(scene.clone(), &mut camera, &settings).unwrap();
+
+        assert_eq!(output.end, RenderEnd::Finished);
+        assert_eq!(output.image.data.len(), 24 * 16 * 4);
+        assert_eq!(
+            output.image.to_rgba8(),
+            Image::from_camera(&camera, PixelFormat::Rgba).to_rgba8()
+        );
+
+        let mut reference = small_camera(2).build();
+        reference.send_rays(scene);
+        assert_eq!(camera.pixels, reference.pixels);
+    }
+
+    #[test]
+    fn cancelled_renders_say_so() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let settings = RenderSettings::default().progressive(Progressive::default().cancel(cancel));
+        let mut camera = small_camera(64).build();
+        let output = render(Arc::new(Scene::init(0.5)), &mut camera, &settings).unwrap();
+        assert_eq!(output.end, RenderEnd::Cancelled);
+    }
+}
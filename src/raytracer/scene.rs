@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
 use crate::color::RGB;
+use crate::config::textures::REFERENCE_WHITE_NITS;
 use crate::objects::*;
-use crate::textures::Texture::*;
+use crate::textures::{Emission, Texture::*};
 use crate::type_aliases::{Color, Point};
 
 pub struct Scene {
     pub objects: Objects,
+    pub volumes: Vec<Volume>,
+    pub fog: Option<Medium>,
     pub brightness: f64,
 }
 
@@ -18,9 +21,13 @@ impl Scene {
             Diffusive(RGB::light_blue()),
         );
         //Diffusive Reflective Light
-        let light = Cylinder::new(Point::default(), 1.0, 2.0,  Diffusive(RGB::black()));
+        let light = Cylinder::new(Point::default(), 1.0, 2.0, Diffusive(RGB::black()));
         let sphere = Sphere::new(Point::new(3.0, 1.0, 0.0), 1.0, Reflective);
-        let cube = Cube::new(Point::new(-3.0, 0.5, 0.0), 1.0, Light(RGB::red()));
+        let cube = Cube::new(
+            Point::new(-3.0, 0.5, 0.0),
+            1.0,
+            Light(Emission::new(RGB::red(), REFERENCE_WHITE_NITS)),
+        );
 
         let objects: Objects = vec![
             Arc::new(flat_plane),
@@ -29,11 +36,10 @@ impl Scene {
             Arc::new(cube),
         ];
 
-        // Adjust invalid value in brightness
-        let brightness = if brightness > 1.0 { 1.0 } else { brightness };
-
         Self {
             objects,
+            volumes: Vec::new(),
+            fog: None,
             brightness: if brightness <= 0.0 {
                 0.0001
             } else {
@@ -45,4 +51,11 @@ impl Scene {
     pub fn background(&self) -> Color {
         Color::white() * self.brightness
     }
+
+    /// Emissive objects with a surface that can be sampled as area lights
+    pub fn lights(&self) -> impl Iterator<Item = &Arc<dyn Object>> {
+        self.objects
+            .iter()
+            .filter(|object| object.texture().emission().is_some() && object.area() > 0.0)
+    }
 }
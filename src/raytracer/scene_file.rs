@@ -0,0 +1,566 @@
+use super::{Camera, CameraBuilder, Scene, Subject};
+use crate::integrators;
+use crate::objects::*;
+use crate::samplers;
+use crate::textures::{Emission, Principled, Texture};
+use crate::type_aliases::{Color, Point};
+use crate::Error;
+use nalgebra::Vector3;
+use std::fmt::Write;
+use std::path::Path;
+use std::str::{FromStr, SplitWhitespace};
+use std::sync::Arc;
+
+/// Brightness of scenes that do not set it
+const DEFAULT_BRIGHTNESS: f64 = 0.5;
+
+/// Read a scene and the settings of its camera from the text of a scene file.
+///
+/// Every line of a scene file is one setting or one object, made of a keyword and numbers.
+/// Colors are three numbers in the 0-255 range, points and directions are three numbers too.
+/// Anything after a `#` is a comment.
+///
+/// ```text
+/// resolution 800 600
+/// samples 100
+/// position -6 6 15
+/// look_at 0 0 0
+/// brightness 0.5
+/// sphere 3 1 0  1  reflective
+/// cube -3 0.5 0  1  light 255 0 0 100
+/// ```
+///
+/// Camera settings are `resolution`, `samples`, `max_depth`, `seed`, `integrator`, `sampler`,
+/// `noise_threshold`, `max_samples`, `position`, `look_at`, `focal_length`, `sensor_width` and
+/// `exposure`. The name of an integrator can be followed by its settings: `photon <photons>
+/// <radius>`, `ppm <photons> <radius> <passes>`, `ao <distance>` or `depth <distance>`.
+///
+/// The scene has `brightness`, `fog <density> <albedo> <anisotropy>`, and the objects
+/// `sphere <center> <radius>`, `cube <center> <size>`, `cylinder <center> <radius> <height>`,
+/// `plane <center> <radius>`, `quad <corner> <edge u> <edge v>` and `triangle <a> <b> <c>`, each
+/// followed by a material: `diffuse <color>`, `reflective`, `light <color> <nits>` or
+/// `principled <base color> <metallic> <roughness> <specular> <clearcoat> <sheen>
+/// <transmission> <ior> <emission>`.
+///
+/// `volume <density> <albedo> <anisotropy> <object>` fills an object with a medium. Voxel grids
+/// go between the medium and the object, as `grid <min> <max> <x> <y> <z> <densities>`.
+pub fn parse_scene(text: &str) -> Result<(Scene, CameraBuilder), Error> {
+    let mut scene = Scene {
+        objects: Vec::new(),
+        volumes: Vec::new(),
+        fog: None,
+        brightness: DEFAULT_BRIGHTNESS,
+    };
+    let mut camera = CameraBuilder::new();
+    // Line of every object, volume and fog, to report invalid settings on
+    let mut lines = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = Tokens {
+            line: index + 1,
+            tokens: line.split_whitespace(),
+        };
+        let Some(keyword) = tokens.tokens.next() else {
+            continue;
+        };
+
+        match keyword {
+            "resolution" => {
+                let (width, height) = (tokens.whole("a width")?, tokens.whole("a height")?);
+                camera.resolution(width, height);
+            }
+            "samples" => camera.sample_size = Some(tokens.whole("a sample size")?),
+            "max_depth" => camera.max_depth = Some(tokens.whole("a depth")?),
+            "seed" => camera.seed = Some(tokens.whole("a seed")?),
+            "integrator" => {
+                let name = tokens.word("an integrator")?;
+                let mut settings = Vec::new();
+                while tokens.tokens.clone().next().is_some() {
+                    settings.push(tokens.number("an integrator setting")?);
+                }
+                let integrator = match integrators::from_name(name) {
+                    Some(_) => integrators::with_settings(name, &settings).ok_or_else(|| {
+                        tokens.error(&format!("Wrong settings for the `{name}` integrator"))
+                    })?,
+                    None => return Err(tokens.error(&format!("Unknown integrator `{name}`"))),
+                };
+                camera.integrator = Some(integrator);
+            }
+            "sampler" => {
+                let name = tokens.word("a sampler")?;
+                let sampler = samplers::from_name(name)
+                    .ok_or_else(|| tokens.error(&format!("Unknown sampler `{name}`")))?;
+                camera.sampler = Some(sampler);
+            }
+            "noise_threshold" => camera.noise_threshold = Some(tokens.number("a threshold")?),
+            "max_samples" => camera.max_sample_size = Some(tokens.whole("a sample size")?),
+            "position" => camera.position = Some(tokens.vector("a position")?),
+            "look_at" => camera.look_at = Some(tokens.vector("a point to look at")?),
+            "focal_length" => camera.focal_length = Some(tokens.number("a focal length")?),
+            "sensor_width" => camera.sensor_width = Some(tokens.number("a sensor width")?),
+            "exposure" => camera.exposure = Some(tokens.number("an exposure")?),
+            "brightness" => {
+                let brightness = tokens.number("a brightness")?;
+                scene.brightness = if brightness <= 0.0 {
+                    0.0001
+                } else {
+                    brightness
+                };
+            }
+            "fog" => {
+                scene.fog = Some(tokens.medium()?);
+                lines.push((Subject::Fog, tokens.line));
+            }
+            "volume" => {
+                let medium = tokens.medium()?;
+                let grid = match tokens.skip("grid") {
+                    true => Some(tokens.grid()?),
+                    false => None,
+                };
+                let keyword = tokens.word("an object")?;
+                let boundary = tokens
+                    .object(keyword)?
+                    .ok_or_else(|| tokens.error(&format!("`{keyword}` is not an object")))?;
+                lines.push((Subject::Volume(scene.volumes.len()), tokens.line));
+                scene.volumes.push(Volume {
+                    boundary,
+                    medium,
+                    grid,
+                });
+            }
+            keyword => {
+                let object = tokens
+                    .object(keyword)?
+                    .ok_or_else(|| tokens.error(&format!("Unknown keyword `{keyword}`")))?;
+                lines.push((Subject::Object(scene.objects.len()), tokens.line));
+                scene.objects.push(object);
+            }
+        }
+        tokens.end()?;
+    }
+
+    // The camera is left out, settings given after loading can still change it
+    if let Some(issue) = scene.validate_contents().into_iter().next() {
+        let line = lines
+            .iter()
+            .rfind(|(subject, _)| *subject == issue.subject)
+            .map_or(0, |(_, line)| *line);
+        return Err(Error::Parse {
+            line,
+            message: format!("{} {}", issue.field, issue.reason),
+        });
+    }
+
+    Ok((scene, camera))
+}
+
+/// Read a scene file from disk, see `parse_scene`
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<(Scene, CameraBuilder), Error> {
+    let text = std::fs::read_to_string(path.as_ref()).map_err(Error::file(path.as_ref()))?;
+    parse_scene(&text)
+}
+
+/// Write `scene` and the settings of `camera` as the text of a scene file. Fails for objects that
+/// can not be written, see `Object::describe`.
+pub fn scene_to_text(scene: &Scene, camera: &Camera) -> Result<String, Error> {
+    let (width, height) = camera.resolution;
+    let mut text = String::new();
+    let mut line = |line: String| {
+        text.push_str(&line);
+        text.push('\n');
+    };
+
+    line(format!("resolution {width} {height}"));
+    line(format!("samples {}", camera.sample_size));
+    line(format!("max_depth {}", camera.max_depth));
+    line(format!("seed {}", camera.seed));
+    let mut integrator = camera.integrator.name().to_string();
+    for setting in camera.integrator.settings() {
+        write!(integrator, " {setting}").expect("Writing to a string does not fail");
+    }
+    line(format!("integrator {integrator}"));
+    line(format!("sampler {}", camera.sampler.name()));
+    if let Some(threshold) = camera.noise_threshold {
+        line(format!("noise_threshold {threshold}"));
+    }
+    line(format!("max_samples {}", camera.max_sample_size));
+    line(format!("position {}", vector_to_text(&camera.position)));
+    line(format!("look_at {}", vector_to_text(&camera.look_at)));
+    line(format!("focal_length {}", camera.focal_length));
+    line(format!("sensor_width {}", camera.sensor_width));
+    line(format!("exposure {}", camera.exposure));
+
+    line(format!("brightness {}", scene.brightness));
+    if let Some(fog) = &scene.fog {
+        line(format!("fog {}", medium_to_text(fog)));
+    }
+
+    for (index, object) in scene.objects.iter().enumerate() {
+        let description = object.describe().ok_or_else(|| {
+            Error::InvalidScene(format!("Object {index} can not be written to a scene file"))
+        })?;
+        line(description);
+    }
+
+    for (index, volume) in scene.volumes.iter().enumerate() {
+        let boundary = volume.boundary.describe().ok_or_else(|| {
+            Error::InvalidScene(format!("Volume {index} can not be written to a scene file"))
+        })?;
+        let grid = match &volume.grid {
+            Some(grid) => grid_to_text(grid) + " ",
+            None => String::new(),
+        };
+        line(format!(
+            "volume {} {grid}{boundary}",
+            medium_to_text(&volume.medium)
+        ));
+    }
+
+    Ok(text)
+}
+
+/// Three numbers in a scene file, such as a point or a color
+pub(crate) fn vector_to_text(vector: &Vector3<f64>) -> String {
+    format!("{} {} {}", vector.x, vector.y, vector.z)
+}
+
+/// Material in a scene file
+pub(crate) fn texture_to_text(texture: &Texture) -> String {
+    match texture {
+        Texture::Light(emission) => format!(
+            "light {} {}",
+            vector_to_text(&emission.color),
+            emission.strength
+        ),
+        Texture::Diffusive(color) => format!("diffuse {}", vector_to_text(color)),
+        Texture::Reflective => "reflective".to_string(),
+        Texture::Principled(principled) => format!(
+            "principled {} {} {} {} {} {} {} {} {}",
+            vector_to_text(&principled.base_color),
+            principled.metallic,
+            principled.roughness,
+            principled.specular,
+            principled.clearcoat,
+            principled.sheen,
+            principled.transmission,
+            principled.ior,
+            vector_to_text(&principled.emission)
+        ),
+    }
+}
+
+fn medium_to_text(medium: &Medium) -> String {
+    format!(
+        "{} {} {}",
+        medium.density,
+        vector_to_text(&medium.albedo),
+        medium.anisotropy
+    )
+}
+
+fn grid_to_text(grid: &GridDensity) -> String {
+    let (x, y, z) = grid.grid.resolution;
+    let mut text = format!(
+        "grid {} {} {x} {y} {z}",
+        vector_to_text(&grid.min_bounds),
+        vector_to_text(&grid.max_bounds)
+    );
+    for density in &grid.grid.densities {
+        write!(text, " {density}").expect("Writing to a string does not fail");
+    }
+    text
+}
+
+/// The words of one line of a scene file
+struct Tokens<'a> {
+    line: usize,
+    tokens: SplitWhitespace<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::Parse {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn word(&mut self, expected: &str) -> Result<&'a str, Error> {
+        self.tokens
+            .next()
+            .ok_or_else(|| self.error(&format!("Expected {expected} at the end of the line")))
+    }
+
+    /// Skip the next word if it is `word`
+    fn skip(&mut self, word: &str) -> bool {
+        let found = self.tokens.clone().next() == Some(word);
+        if found {
+            self.tokens.next();
+        }
+        found
+    }
+
+    fn parse<T: FromStr>(&mut self, expected: &str) -> Result<T, Error> {
+        let word = self.word(expected)?;
+        word.parse()
+            .map_err(|_| self.error(&format!("Expected {expected}, found `{word}`")))
+    }
+
+    fn number(&mut self, expected: &str) -> Result<f64, Error> {
+        let number: f64 = self.parse(expected)?;
+        match number.is_finite() {
+            true => Ok(number),
+            false => Err(self.error(&format!("Expected {expected}, found `{number}`"))),
+        }
+    }
+
+    fn whole<T: FromStr>(&mut self, expected: &str) -> Result<T, Error> {
+        self.parse(&format!("{expected} (a whole number)"))
+    }
+
+    fn vector(&mut self, expected: &str) -> Result<Point, Error> {
+        Ok(Point::new(
+            self.number(expected)?,
+            self.number(expected)?,
+            self.number(expected)?,
+        ))
+    }
+
+    fn color(&mut self) -> Result<Color, Error> {
+        self.vector("a color")
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        match self.tokens.next() {
+            None => Ok(()),
+            Some(word) => Err(self.error(&format!("Unexpected `{word}` at the end of the line"))),
+        }
+    }
+
+    fn texture(&mut self) -> Result<Texture, Error> {
+        let texture = match self.word("a material")? {
+            "diffuse" => Texture::Diffusive(self.color()?),
+            "reflective" => Texture::Reflective,
+            "light" => Texture::Light(Emission::new(self.color()?, self.number("a strength")?)),
+            "principled" => Texture::Principled(Principled {
+                base_color: self.color()?,
+                metallic: self.number("a metallic factor")?,
+                roughness: self.number("a roughness")?,
+                specular: self.number("a specular factor")?,
+                clearcoat: self.number("a clearcoat factor")?,
+                sheen: self.number("a sheen factor")?,
+                transmission: self.number("a transmission factor")?,
+                ior: self.number("an index of refraction")?,
+                emission: self.color()?,
+            }),
+            word => return Err(self.error(&format!("Unknown material `{word}`"))),
+        };
+        Ok(texture)
+    }
+
+    /// Object starting with `keyword`, or `None` when the keyword is not an object
+    fn object(&mut self, keyword: &str) -> Result<Option<Arc<dyn Object>>, Error> {
+        let object: Arc<dyn Object> = match keyword {
+            "sphere" => {
+                let (center, radius) = (self.vector("a center")?, self.number("a radius")?);
+                Arc::new(Sphere::new(center, radius, self.texture()?))
+            }
+            "cube" => {
+                let (center, size) = (self.vector("a center")?, self.number("a size")?);
+                Arc::new(Cube::new(center, size, self.texture()?))
+            }
+            "cylinder" => {
+                let center = self.vector("a center")?;
+                let (radius, height) = (self.number("a radius")?, self.number("a height")?);
+                Arc::new(Cylinder::new(center, radius, height, self.texture()?))
+            }
+            "plane" => {
+                let (center, radius) = (self.vector("a center")?, self.number("a radius")?);
+                Arc::new(FlatPlane::new(center, radius, self.texture()?))
+            }
+            "quad" => {
+                let corner = self.vector("a corner")?;
+                let (edge_u, edge_v) = (self.vector("an edge")?, self.vector("an edge")?);
+                Arc::new(Quad::new(corner, edge_u, edge_v, self.texture()?))
+            }
+            "triangle" => {
+                let (a, b, c) = (
+                    self.vector("a vertex")?,
+                    self.vector("a vertex")?,
+                    self.vector("a vertex")?,
+                );
+                Arc::new(Triangle::new(a, b, c, self.texture()?))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(object))
+    }
+
+    fn medium(&mut self) -> Result<Medium, Error> {
+        Ok(Medium::new(
+            self.number("a density")?,
+            self.color()?,
+            self.number("an anisotropy")?,
+        ))
+    }
+
+    /// Density of one voxel, which can not be negative or too large for an `f32`
+    fn density(&mut self) -> Result<f32, Error> {
+        let density = self.number("a density")?;
+        match density >= 0.0 && (density as f32).is_finite() {
+            true => Ok(density as f32),
+            false => Err(self.error(&format!(
+                "Expected a density between 0 and {:e}, found `{density}`",
+                f32::MAX
+            ))),
+        }
+    }
+
+    fn grid(&mut self) -> Result<GridDensity, Error> {
+        let (min_bounds, max_bounds) = (self.vector("a corner")?, self.vector("a corner")?);
+        let resolution: (usize, usize, usize) = (
+            self.whole("a grid size")?,
+            self.whole("a grid size")?,
+            self.whole("a grid size")?,
+        );
+        let count = resolution
+            .0
+            .checked_mul(resolution.1)
+            .and_then(|count| count.checked_mul(resolution.2))
+            .ok_or_else(|| self.error("Grid resolution is too large"))?;
+        let densities = (0..count)
+            .map(|_| self.density())
+            .collect::<Result<_, _>>()?;
+
+        let grid = VoxelGrid::new(resolution, densities)
+            .map_err(|_| self.error("Expected a grid size above 0"))?;
+        Ok(GridDensity {
+            grid: Arc::new(grid),
+            min_bounds,
+            max_bounds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = "
+        resolution 40 30   # Comments are skipped
+        samples 12
+        max_depth 7
+        seed 5
+        integrator bdpt
+        sampler halton
+        noise_threshold 0.05
+        position -6 6 15
+        look_at 0 0.5 0
+        focal_length 2
+        exposure 0.5
+        brightness 0.3
+        fog 0.01 200 200 200 0.1
+        sphere 3 1 0 1 reflective
+        cube -3 0.5 0 1 light 255 0 0 100
+        cylinder 0 0 -3 0.5 2 diffuse 10 200 30
+        plane 0 0 0 10 principled 200 200 200 0 0.5 0.5 0 0 0 1.5 0 0 0
+        quad -1 0 2 1 0 0 0 1 0 diffuse 255 255 255
+        triangle 0 0 0 1 0 0 0 1 0 diffuse 0 0 255
+        volume 2 255 255 255 0.2 grid 0 0 0 1 1 1 2 1 1 0.5 1 sphere 0 2 0 1 diffuse 0 0 0
+    ";
+
+    /// Scene file `text` describes, as `scene_to_text` writes it
+    fn round_trip(text: &str) -> String {
+        let (scene, builder) = parse_scene(text).unwrap();
+        scene_to_text(&scene, &builder.build()).unwrap()
+    }
+
+    fn parse_error(text: &str) -> String {
+        match parse_scene(text) {
+            Ok(_) => panic!("`{text}` was parsed"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn written_scenes_read_back_the_same() {
+        let written = round_trip(SCENE);
+        assert_eq!(round_trip(&written), written);
+
+        let (scene, builder) = parse_scene(&written).unwrap();
+        let camera = builder.build();
+        assert_eq!(scene.objects.len(), 6);
+        assert_eq!(scene.volumes.len(), 1);
+        assert!(scene.fog.is_some());
+        assert_eq!(camera.resolution, (40, 30));
+        assert_eq!(camera.sample_size, 12);
+        assert_eq!(camera.seed, 5);
+        assert_eq!(camera.integrator.name(), "bdpt");
+        assert_eq!(camera.sampler.name(), "halton");
+        assert_eq!(camera.noise_threshold, Some(0.05));
+    }
+
+    #[test]
+    fn default_scene_reads_back_the_same() {
+        let camera = CameraBuilder::new().seed(1).build();
+        let written = scene_to_text(&Scene::init(0.5), &camera).unwrap();
+        assert_eq!(round_trip(&written), written);
+    }
+
+    #[test]
+    fn integrators_keep_their_settings() {
+        for integrator in [
+            "ppm 1000 0.05 4",
+            "photon 500 0.2",
+            "ao 3.5",
+            "depth 12",
+            "path",
+        ] {
+            let written = round_trip(&format!("integrator {integrator}"));
+            assert!(
+                written.contains(&format!("integrator {integrator}\n")),
+                "{written}"
+            );
+        }
+        assert_eq!(
+            parse_error("integrator ppm 1000 0.05"),
+            "Line 1: Wrong settings for the `ppm` integrator"
+        );
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_their_line() {
+        assert_eq!(
+            parse_error("samples 4\nsphere 0 0 0 one reflective"),
+            "Line 2: Expected a radius, found `one`"
+        );
+        assert_eq!(
+            parse_error("teapot 0 0 0"),
+            "Line 1: Unknown keyword `teapot`"
+        );
+        assert_eq!(
+            parse_error("sphere 0 0 0 1"),
+            "Line 1: Expected a material at the end of the line"
+        );
+    }
+
+    #[test]
+    fn grids_that_do_not_fit_are_rejected() {
+        let grid = |grid: &str| {
+            format!("volume 1 1 1 1 0 grid 0 0 0 1 1 1 {grid} cube 0 0 0 1 reflective")
+        };
+        assert_eq!(
+            parse_error(&grid("99999999999 99999999999 99999999999")),
+            "Line 1: Grid resolution is too large"
+        );
+        assert_eq!(
+            parse_error(&grid("0 1 1")),
+            "Line 1: Expected a grid size above 0"
+        );
+        for density in ["-1", "NaN", "inf", "1e300"] {
+            let error = parse_error(&grid(&format!("1 1 1 {density}")));
+            assert!(error.starts_with("Line 1: Expected a density"), "{error}");
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::objects::Object;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Counts of the rays this thread traced since they were last collected
+    static COUNTERS: RefCell<Counters> = RefCell::default();
+}
+
+#[derive(Debug, Clone, Default)]
+struct Counters {
+    camera_rays: u64,
+    /// Rays tested against every object for the closest hit, camera rays included
+    rays: u64,
+    shadow_rays: u64,
+    /// Calls of `Object::intersection` by the name of the object
+    intersection_tests: Vec<(&'static str, u64)>,
+}
+
+impl Counters {
+    fn add(&mut self, other: Counters) {
+        self.camera_rays += other.camera_rays;
+        self.rays += other.rays;
+        self.shadow_rays += other.shadow_rays;
+        for (name, tests) in other.intersection_tests {
+            self.count_tests(name, tests);
+        }
+    }
+
+    fn count_tests(&mut self, name: &'static str, tests: u64) {
+        match self
+            .intersection_tests
+            .iter_mut()
+            .find(|(known, _)| *known == name)
+        {
+            Some((_, total)) => *total += tests,
+            None => self.intersection_tests.push((name, tests)),
+        }
+    }
+
+    fn count_objects(&mut self, objects: &[Arc<dyn Object>]) {
+        for object in objects {
+            self.count_tests(object.name(), 1);
+        }
+    }
+}
+
+pub(crate) fn count_camera_rays(rays: u64) {
+    COUNTERS.with(|counters| counters.borrow_mut().camera_rays += rays);
+}
+
+/// Count a ray that looks for the closest hit among `tested`, which are all tested
+pub(crate) fn count_ray(tested: &[Arc<dyn Object>]) {
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        counters.rays += 1;
+        counters.count_objects(tested);
+    });
+}
+
+/// Count a shadow ray that was tested against `tested`, up to the first object that blocked it
+pub(crate) fn count_shadow_ray(tested: &[Arc<dyn Object>]) {
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        counters.shadow_rays += 1;
+        counters.count_objects(tested);
+    });
+}
+
+/// Count one intersection test with `object`, outside of the rays of the scene
+pub(crate) fn count_intersection_test(object: &Arc<dyn Object>) {
+    COUNTERS.with(|counters| counters.borrow_mut().count_tests(object.name(), 1));
+}
+
+/// Take the counts of the calling thread and of every thread of the current rayon pool
+fn collect() -> Counters {
+    let take = || COUNTERS.with(|counters| std::mem::take(&mut *counters.borrow_mut()));
+    let mut total = take();
+    for counters in rayon::broadcast(|_| take()) {
+        total.add(counters);
+    }
+    total
+}
+
+/// Statistics of a render while it runs. Rays are counted by the threads that trace them, so
+/// renders that run at the same time on the same threads count each other's rays.
+pub(crate) struct Recorder {
+    phases: Vec<(&'static str, Duration)>,
+    phase_start: Instant,
+}
+
+impl Recorder {
+    /// Start counting, without the rays traced before
+    pub fn start() -> Self {
+        collect();
+        Self {
+            phases: Vec::new(),
+            phase_start: Instant::now(),
+        }
+    }
+
+    /// End the phase that is running, which is named `name`
+    pub fn end_phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.phase_start));
+        self.phase_start = now;
+    }
+
+    pub fn finish(self) -> RenderStats {
+        let mut counters = collect();
+        // Threads see the objects in different orders
+        counters.intersection_tests.sort_unstable();
+
+        RenderStats {
+            camera_rays: counters.camera_rays,
+            secondary_rays: counters.rays.saturating_sub(counters.camera_rays),
+            shadow_rays: counters.shadow_rays,
+            intersection_tests: counters.intersection_tests,
+            phases: self.phases,
+        }
+    }
+}
+
+/// ### RenderStats
+///
+/// What the last render traced and where its time went, see `Camera::stats`. Distributed renders
+/// only have the times, their rays are traced by the workers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    /// Rays from the camera, one for every sample
+    pub camera_rays: u64,
+    /// Rays that continue a path after it bounced, and the rays of light paths
+    pub secondary_rays: u64,
+    /// Rays that check whether something blocks the way to a light
+    pub shadow_rays: u64,
+    /// Ray-object intersection tests by the kind of object, such as `sphere`, in alphabetical
+    /// order. Boundaries of volumes count as well.
+    pub intersection_tests: Vec<(&'static str, u64)>,
+    /// Time of every phase of the render, in the order they ran
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl RenderStats {
+    pub fn rays(&self) -> u64 {
+        self.camera_rays + self.secondary_rays + self.shadow_rays
+    }
+
+    /// Rays traced along a path on average, the camera ray included and shadow rays left out
+    pub fn average_path_length(&self) -> f64 {
+        match self.camera_rays {
+            0 => 0.0,
+            camera_rays => (camera_rays + self.secondary_rays) as f64 / camera_rays as f64,
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.phases.iter().map(|(_, time)| *time).sum()
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        match self.total_time().as_secs_f64() {
+            seconds if seconds > 0.0 => self.rays() as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Rays:                {:>14} ({:.2} M per second)",
+            self.rays(),
+            self.rays_per_second() / 1e6
+        )?;
+        writeln!(f, "  camera:            {:>14}", self.camera_rays)?;
+        writeln!(f, "  secondary:         {:>14}", self.secondary_rays)?;
+        writeln!(f, "  shadow:            {:>14}", self.shadow_rays)?;
+        writeln!(
+            f,
+            "Average path length: {:>14.2}",
+            self.average_path_length()
+        )?;
+        writeln!(f, "Intersection tests:")?;
+        for (name, tests) in &self.intersection_tests {
+            writeln!(f, "  {:<18} {tests:>14}", format!("{name}:"))?;
+        }
+        write!(f, "Time:                {:>14.2?}", self.total_time())?;
+        for (name, time) in &self.phases {
+            write!(f, "\n  {:<18} {:>14.2?}", format!("{name}:"), time)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raytracer::{parse_scene, Camera, Scene};
+    use crate::test_support::small_camera;
+    use crate::type_aliases::Point;
+
+    /// Stats of rendering `scene` with `camera`, on a pool of its own so no other test adds to
+    /// the counts
+    fn stats(scene: Scene, mut camera: Camera) -> RenderStats {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        pool.install(|| camera.send_rays(Arc::new(scene)));
+        camera.stats
+    }
+
+    fn tests_of(stats: &RenderStats, name: &str) -> u64 {
+        stats
+            .intersection_tests
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map_or(0, |(_, tests)| *tests)
+    }
+
+    #[test]
+    fn every_sample_has_a_camera_ray() {
+        let stats = stats(Scene::init(0.5), small_camera(3).build());
+        assert_eq!(stats.camera_rays, 24 * 16 * 3);
+        assert!(stats.average_path_length() >= 1.0);
+    }
+
+    #[test]
+    fn intersection_tests_are_counted_per_call() {
+        let (scene, mut builder) = parse_scene(
+            "resolution 8 6
+            samples 2
+            sphere 0 0 0 1 diffuse 255 255 255
+            sphere 3 0 0 1 diffuse 255 255 255
+            volume 0.1 255 255 255 0 cube 0 0 5 1 diffuse 0 0 0",
+        )
+        .unwrap();
+        builder.position_by_coordinates(Point::new(0.0, 0.0, 10.0));
+        let stats = stats(scene, builder.build());
+
+        // Both spheres are tested by every ray that looks for the closest hit, and by the shadow
+        // rays the first one does not block
+        let rays = stats.camera_rays + stats.secondary_rays;
+        assert!(tests_of(&stats, "sphere") >= 2 * rays);
+        assert!(tests_of(&stats, "sphere") <= 2 * rays + 2 * stats.shadow_rays);
+        // The boundary of the volume is only tested by the volume itself
+        assert!(tests_of(&stats, "cube") >= stats.camera_rays);
+    }
+}
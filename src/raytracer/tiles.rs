@@ -0,0 +1,172 @@
+use crate::type_aliases::Resolution;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Order in which the tiles of an image are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Row by row, from the top left
+    Scanline,
+    /// Ring by ring, from the center of the image outwards
+    #[default]
+    Spiral,
+    /// Along a Hilbert curve, which keeps tiles rendered around the same time close together
+    Hilbert,
+}
+
+/// Rectangle of pixels rendered as one piece of work. The last tiles of a row or column are
+/// smaller when the tile size does not divide the resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Indices of the pixels of the tile in an image `image_width` pixels wide, row by row
+    pub fn pixels(&self, image_width: u32) -> impl Iterator<Item = usize> + '_ {
+        (self.y..self.y + self.height).flat_map(move |y| {
+            (self.x..self.x + self.width).map(move |x| (y * image_width + x) as usize)
+        })
+    }
+}
+
+/// Split an image into tiles of `tile_size` pixels, sorted in `order`
+pub fn split_into_tiles(resolution: Resolution, tile_size: u32, order: TileOrder) -> Vec<Tile> {
+    let (width, height) = resolution;
+    let tile_size = tile_size.max(1);
+    let (columns, rows) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+    if columns == 0 || rows == 0 {
+        return Vec::new();
+    }
+
+    let mut grid: Vec<(u32, u32)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let center = ((columns - 1) as f64 / 2.0, (rows - 1) as f64 / 2.0);
+            let ring_and_angle = |(column, row): (u32, u32)| {
+                let (dx, dy) = (column as f64 - center.0, row as f64 - center.1);
+                (dx.abs().max(dy.abs()).round() as u32, dy.atan2(dx))
+            };
+            grid.sort_by(|a, b| {
+                let (a, b) = (ring_and_angle(*a), ring_and_angle(*b));
+                a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+            });
+        }
+        TileOrder::Hilbert => {
+            let size = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|(column, row)| hilbert_index(size, *column, *row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| {
+            let (x, y) = (column * tile_size, row * tile_size);
+            Tile {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            }
+        })
+        .collect()
+}
+
+/// Position of `(x, y)` along the Hilbert curve through a `size` by `size` grid
+fn hilbert_index(size: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut step = size / 2;
+    while step > 0 {
+        let rx = (x & step > 0) as u32;
+        let ry = (y & step > 0) as u32;
+        index += step as u64 * step as u64 * ((3 * rx) ^ ry) as u64;
+
+        // Rotate the quadrant so the curve inside it lines up with the others
+        if ry == 0 {
+            if rx == 1 {
+                x = size - 1 - x;
+                y = size - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        step /= 2;
+    }
+    index
+}
+
+/// How far a render is, reported after every tile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Pass over the tiles, starting at 1. Adaptive and progressive renders go over them more
+    /// than once.
+    pub pass: u32,
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    /// Part of the whole render that is done, from 0.0 to 1.0
+    pub done: f64,
+    pub elapsed: Duration,
+    /// Estimated time until the render is done
+    pub eta: Option<Duration>,
+}
+
+/// Function the camera calls with the progress of a render. It is called from the threads that
+/// render the tiles.
+#[derive(Clone)]
+pub struct ProgressCallback(pub Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert];
+
+    #[test]
+    fn tiles_cover_every_pixel_once() {
+        for (width, height) in [(37, 23), (8, 8), (1, 50), (16, 17)] {
+            for order in ORDERS {
+                let mut covered = vec![0; (width * height) as usize];
+                for tile in split_into_tiles((width, height), 8, order) {
+                    for pixel in tile.pixels(width) {
+                        covered[pixel] += 1;
+                    }
+                }
+                assert!(
+                    covered.iter().all(|count| *count == 1),
+                    "{width}x{height} in {order:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hilbert_order_goes_from_tile_to_neighbouring_tile() {
+        let tiles = split_into_tiles((64, 64), 8, TileOrder::Hilbert);
+        assert_eq!(tiles.len(), 64);
+        for pair in tiles.windows(2) {
+            let step = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(step, 8, "{:?} to {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn hilbert_indices_number_every_cell() {
+        let mut indices: Vec<u64> = (0..16)
+            .flat_map(|y| (0..16).map(move |x| hilbert_index(16, x, y)))
+            .collect();
+        indices.sort_unstable();
+        assert!(indices.iter().copied().eq(0..256));
+    }
+}
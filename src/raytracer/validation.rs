@@ -0,0 +1,320 @@
+use super::{Camera, Scene};
+use crate::objects::Medium;
+use crate::textures::Texture;
+use crate::type_aliases::{Color, Point};
+use std::fmt;
+
+/// Part of a scene an `Issue` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    /// Object at this index of `Scene::objects`
+    Object(usize),
+    /// Volume at this index of `Scene::volumes`
+    Volume(usize),
+    Fog,
+    /// Settings of the scene itself, such as the brightness
+    Scene,
+    Camera,
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Object(index) => write!(f, "Object {index}"),
+            Subject::Volume(index) => write!(f, "Volume {index}"),
+            Subject::Fog => f.write_str("Fog"),
+            Subject::Scene => f.write_str("Scene"),
+            Subject::Camera => f.write_str("Camera"),
+        }
+    }
+}
+
+/// ### Issue
+///
+/// Setting that makes a scene impossible to render, or renders it as something else than what it
+/// describes. Fields are named like in scene files, so `radius`, `look_at` or `resolution`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub subject: Subject,
+    pub field: &'static str,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} {}", self.subject, self.field, self.reason)
+    }
+}
+
+/// Invalid fields of one part of a scene, see `Object::validate`
+#[derive(Debug, Default)]
+pub struct Checks {
+    failed: Vec<(&'static str, &'static str)>,
+}
+
+impl Checks {
+    /// Report `field` as invalid unless `is_valid`
+    pub fn require(
+        &mut self,
+        is_valid: bool,
+        field: &'static str,
+        reason: &'static str,
+    ) -> &mut Self {
+        if !is_valid {
+            self.failed.push((field, reason));
+        }
+        self
+    }
+
+    pub fn finite(&mut self, field: &'static str, value: f64) -> &mut Self {
+        self.require(value.is_finite(), field, "must be finite")
+    }
+
+    pub fn point(&mut self, field: &'static str, point: &Point) -> &mut Self {
+        self.require(point.iter().all(|x| x.is_finite()), field, "must be finite")
+    }
+
+    pub fn positive(&mut self, field: &'static str, value: f64) -> &mut Self {
+        match value.is_finite() {
+            true => self.require(value > 0.0, field, "must be greater than 0"),
+            false => self.finite(field, value),
+        }
+    }
+
+    pub fn not_negative(&mut self, field: &'static str, value: f64) -> &mut Self {
+        match value.is_finite() {
+            true => self.require(value >= 0.0, field, "must not be negative"),
+            false => self.finite(field, value),
+        }
+    }
+
+    /// Factor from 0 to 1, such as the roughness of a material
+    pub fn factor(&mut self, field: &'static str, value: f64) -> &mut Self {
+        self.require((0.0..=1.0).contains(&value), field, "must be from 0 to 1")
+    }
+
+    pub fn color(&mut self, field: &'static str, color: &Color) -> &mut Self {
+        match color.iter().all(|x| x.is_finite()) {
+            true => self.require(color.min() >= 0.0, field, "must not be negative"),
+            false => self.point(field, color),
+        }
+    }
+
+    pub fn texture(&mut self, texture: &Texture) -> &mut Self {
+        match texture {
+            Texture::Diffusive(color) => self.color("color", color),
+            Texture::Reflective => self,
+            Texture::Light(emission) => self
+                .color("color", &emission.color)
+                .not_negative("strength", emission.strength),
+            Texture::Principled(principled) => self
+                .color("base_color", &principled.base_color)
+                .factor("metallic", principled.metallic)
+                .factor("roughness", principled.roughness)
+                .factor("specular", principled.specular)
+                .factor("clearcoat", principled.clearcoat)
+                .factor("sheen", principled.sheen)
+                .factor("transmission", principled.transmission)
+                .positive("ior", principled.ior)
+                .color("emission", &principled.emission),
+        }
+    }
+
+    pub fn medium(&mut self, medium: &Medium) -> &mut Self {
+        let anisotropy = medium.anisotropy;
+        self.not_negative("density", medium.density)
+            .color("albedo", &medium.albedo)
+            .require(
+                anisotropy > -1.0 && anisotropy < 1.0,
+                "anisotropy",
+                "must be between -1 and 1",
+            )
+    }
+
+    /// The invalid fields as issues of `subject`
+    pub fn into_issues(self, subject: Subject) -> impl Iterator<Item = Issue> {
+        self.failed.into_iter().map(move |(field, reason)| Issue {
+            subject,
+            field,
+            reason,
+        })
+    }
+}
+
+impl Scene {
+    /// Every setting of the scene and of `camera` that can not be rendered, such as a negative
+    /// radius, a number that is not finite, or a camera that looks at its own position. Empty when
+    /// the scene is fine.
+    pub fn validate(&self, camera: &Camera) -> Vec<Issue> {
+        let mut issues = self.validate_contents();
+        issues.extend(camera.validate().into_issues(Subject::Camera));
+        issues
+    }
+
+    /// Issues of everything but the camera
+    pub(crate) fn validate_contents(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        let mut checks = Checks::default();
+        checks.not_negative("brightness", self.brightness);
+        issues.extend(checks.into_issues(Subject::Scene));
+
+        if let Some(fog) = &self.fog {
+            let mut checks = Checks::default();
+            checks.medium(fog);
+            issues.extend(checks.into_issues(Subject::Fog));
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let mut checks = Checks::default();
+            object.validate(&mut checks);
+            issues.extend(checks.into_issues(Subject::Object(index)));
+        }
+
+        for (index, volume) in self.volumes.iter().enumerate() {
+            let mut checks = Checks::default();
+            checks.medium(&volume.medium);
+            if let Some(grid) = &volume.grid {
+                let (min, max) = (grid.min_bounds, grid.max_bounds);
+                checks.point("grid", &min).point("grid", &max).require(
+                    min.iter().zip(max.iter()).all(|(min, max)| min < max),
+                    "grid",
+                    "must have its first corner below the second",
+                );
+            }
+            volume.boundary.validate(&mut checks);
+            issues.extend(checks.into_issues(Subject::Volume(index)));
+        }
+        issues
+    }
+}
+
+impl Camera {
+    fn validate(&self) -> Checks {
+        let (width, height) = self.resolution;
+        let mut checks = Checks::default();
+        checks
+            .require(
+                width > 0 && height > 0,
+                "resolution",
+                "must be greater than 0",
+            )
+            .require(self.sample_size > 0, "samples", "must be greater than 0")
+            .point("position", &self.position)
+            .point("look_at", &self.look_at)
+            .positive("focal_length", self.focal_length)
+            .positive("sensor_width", self.sensor_width)
+            .finite("exposure", self.exposure);
+        if let Some(threshold) = self.noise_threshold {
+            checks.positive("noise_threshold", threshold);
+        }
+        if let Some(denoiser) = &self.denoiser {
+            checks
+                .positive("color_sigma", denoiser.color_sigma)
+                .positive("normal_sigma", denoiser.normal_sigma)
+                .positive("depth_sigma", denoiser.depth_sigma)
+                .positive("albedo_sigma", denoiser.albedo_sigma);
+        }
+
+        // The view needs a direction, and one that is not along the up axis
+        let is_finite = |point: &Point| point.iter().all(|x| x.is_finite());
+        if self.position == self.look_at {
+            checks.require(false, "look_at", "must differ from the position");
+        } else if is_finite(&self.position) && is_finite(&self.look_at) {
+            checks.require(
+                is_finite(&self.up_direction),
+                "look_at",
+                "must not be straight above or below the position",
+            );
+        }
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{Cube, Sphere, Volume};
+    use crate::raytracer::CameraBuilder;
+    use std::sync::Arc;
+
+    fn issue(subject: Subject, field: &'static str, reason: &'static str) -> Issue {
+        Issue {
+            subject,
+            field,
+            reason,
+        }
+    }
+
+    fn camera() -> Camera {
+        CameraBuilder::new()
+            .position_by_coordinates(Point::new(0.0, 1.0, 5.0))
+            .look_at(Point::new(0.0, 0.0, 0.0))
+            .build()
+    }
+
+    #[test]
+    fn valid_scenes_have_no_issues() {
+        assert_eq!(Scene::init(0.5).validate(&camera()), []);
+    }
+
+    #[test]
+    fn every_invalid_field_is_an_issue() {
+        let white = Color::repeat(255.0);
+        let scene = Scene {
+            objects: vec![
+                Arc::new(Sphere::new(Point::zeros(), 1.0, Texture::Reflective)),
+                Arc::new(Sphere::new(
+                    Point::new(f64::NAN, 0.0, 0.0),
+                    -1.0,
+                    Texture::Diffusive(Color::new(-1.0, 0.0, 0.0)),
+                )),
+            ],
+            volumes: vec![Volume::new(
+                Arc::new(Cube::new(Point::zeros(), 0.0, Texture::Reflective)),
+                // `Medium::new` clamps the anisotropy, the field itself does not
+                Medium {
+                    anisotropy: 1.0,
+                    ..Medium::new(1.0, white, 0.0)
+                },
+            )],
+            fog: Some(Medium::new(-0.1, white, 0.0)),
+            brightness: f64::INFINITY,
+        };
+
+        assert_eq!(
+            scene.validate(&camera()),
+            [
+                issue(Subject::Scene, "brightness", "must be finite"),
+                issue(Subject::Fog, "density", "must not be negative"),
+                issue(Subject::Object(1), "center", "must be finite"),
+                issue(Subject::Object(1), "radius", "must be greater than 0"),
+                issue(Subject::Object(1), "color", "must not be negative"),
+                issue(Subject::Volume(0), "anisotropy", "must be between -1 and 1"),
+                issue(Subject::Volume(0), "size", "must be greater than 0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cameras_need_a_direction_to_look_in() {
+        let camera = CameraBuilder::new()
+            .position_by_coordinates(Point::new(1.0, 2.0, 3.0))
+            .look_at(Point::new(1.0, 2.0, 3.0))
+            .focal_length(0.0)
+            .build();
+        assert_eq!(
+            Scene::init(0.5).validate(&camera),
+            [
+                issue(Subject::Camera, "focal_length", "must be greater than 0"),
+                issue(Subject::Camera, "look_at", "must differ from the position"),
+            ]
+        );
+    }
+
+    #[test]
+    fn issues_read_like_scene_files() {
+        let issue = issue(Subject::Object(3), "radius", "must be greater than 0");
+        assert_eq!(issue.to_string(), "Object 3: radius must be greater than 0");
+    }
+}
@@ -0,0 +1,78 @@
+use super::sobol::sobol_point;
+use super::{PixelSample, Sampler};
+use crate::random::hash;
+use std::sync::OnceLock;
+
+/// Width and height of the blue noise tile
+const TILE_SIZE: usize = 64;
+
+/// Spread of the energy every filled cell adds while building the tile
+const TILE_SIGMA: f64 = 1.5;
+
+/// ### BlueNoise
+///
+/// Sobol points that are the same for every pixel, shifted by a blue noise tile. The shift of
+/// neighbouring pixels differs as much as possible, which leaves the noise that is left after a
+/// few samples spread out evenly instead of clumped together. Each dimension uses the tile at a
+/// different offset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlueNoise;
+
+impl Sampler for BlueNoise {
+    fn get(&self, sample: &PixelSample, dimension: u32) -> f64 {
+        let key = hash(&[sample.seed, dimension as u64 / 2]);
+        let point = sobol_point(sample, dimension, key);
+
+        let offset = hash(&[sample.seed, dimension as u64]);
+        let x = (sample.pixel.0 as usize + offset as usize) % TILE_SIZE;
+        let y = (sample.pixel.1 as usize + (offset >> 32) as usize) % TILE_SIZE;
+        (point + tile()[y * TILE_SIZE + x]).fract()
+    }
+
+    fn name(&self) -> &'static str {
+        "blue-noise"
+    }
+}
+
+/// Blue noise values in the 0.0-1.0 range, built once with the void-and-cluster method
+fn tile() -> &'static [f64] {
+    static TILE: OnceLock<Vec<f64>> = OnceLock::new();
+    TILE.get_or_init(build_tile)
+}
+
+/// Fill the tile one cell at a time, always picking the cell farthest from all filled cells. The
+/// order the cells are filled in becomes their value.
+fn build_tile() -> Vec<f64> {
+    let cells = TILE_SIZE * TILE_SIZE;
+
+    // Energy a filled cell adds at every offset, wrapping around the edges
+    let kernel: Vec<f64> = (0..cells)
+        .map(|cell| {
+            let wrap = |d: usize| d.min(TILE_SIZE - d) as f64;
+            let (dx, dy) = (wrap(cell % TILE_SIZE), wrap(cell / TILE_SIZE));
+            (-(dx * dx + dy * dy) / (2.0 * TILE_SIGMA * TILE_SIGMA)).exp()
+        })
+        .collect();
+
+    let mut energy = vec![0.0; cells];
+    let mut rank = vec![None; cells];
+    let mut next = 0;
+    for order in 0..cells {
+        rank[next] = Some(order);
+        let (x, y) = (next % TILE_SIZE, next / TILE_SIZE);
+        for (cell, energy) in energy.iter_mut().enumerate() {
+            let dx = (cell % TILE_SIZE + TILE_SIZE - x) % TILE_SIZE;
+            let dy = (cell / TILE_SIZE + TILE_SIZE - y) % TILE_SIZE;
+            *energy += kernel[dy * TILE_SIZE + dx];
+        }
+
+        next = (0..cells)
+            .filter(|cell| rank[*cell].is_none())
+            .min_by(|a, b| energy[*a].total_cmp(&energy[*b]))
+            .unwrap_or(0);
+    }
+
+    rank.into_iter()
+        .map(|order| (order.unwrap_or(0) as f64 + 0.5) / cells as f64)
+        .collect()
+}
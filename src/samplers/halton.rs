@@ -0,0 +1,71 @@
+use super::stratified::permute;
+use super::{PixelSample, Sampler};
+use crate::random::{hash, unit_float};
+
+/// Dimensions with their own prime base, the ones after them are random
+const HALTON_DIMENSIONS: usize = 256;
+
+const PRIMES: [u64; HALTON_DIMENSIONS] = primes();
+
+/// ### Halton
+///
+/// The Halton sequence: every dimension mirrors the digits of the sample index in its own prime
+/// base. Each pixel shuffles the digits of every dimension differently, which keeps neighbouring
+/// pixels from repeating each other's pattern and spreads out the first samples in large bases.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Halton;
+
+impl Sampler for Halton {
+    fn get(&self, sample: &PixelSample, dimension: u32) -> f64 {
+        let key = sample.hash(&[dimension as u64]);
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => radical_inverse(base, sample.index, sample.count, key),
+            None => unit_float(hash(&[key, sample.index])),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "halton"
+    }
+}
+
+/// `index` written in `base` and mirrored around the decimal point, with the digits shuffled by
+/// `key`. Digits past the ones that tell `count` samples apart are left random.
+fn radical_inverse(base: u64, mut index: u64, count: u64, key: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut value = 0.0;
+    let mut cell = 1.0;
+    let mut position = 0;
+    let mut remaining = count.saturating_sub(1);
+    while index > 0 || remaining > 0 {
+        let digit = permute(
+            (index % base) as u32,
+            base as u32,
+            hash(&[key, position]) as u32,
+        );
+        cell *= inverse_base;
+        value += digit as f64 * cell;
+        index /= base;
+        remaining /= base;
+        position += 1;
+    }
+    value + cell * unit_float(hash(&[key, position]))
+}
+
+const fn primes() -> [u64; HALTON_DIMENSIONS] {
+    let mut primes = [0; HALTON_DIMENSIONS];
+    let mut count = 0;
+    let mut candidate = 2;
+    while count < HALTON_DIMENSIONS {
+        let mut divisor = 0;
+        while divisor < count && candidate % primes[divisor] != 0 {
+            divisor += 1;
+        }
+        if divisor == count {
+            primes[count] = candidate;
+            count += 1;
+        }
+        candidate += 1;
+    }
+    primes
+}
@@ -0,0 +1,19 @@
+use super::{PixelSample, Sampler};
+use crate::random::unit_float;
+
+/// ### Independent
+///
+/// Plain random numbers, every dimension of every sample unrelated to all the others. Converges
+/// the slowest, but never shows patterns.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Independent;
+
+impl Sampler for Independent {
+    fn get(&self, sample: &PixelSample, dimension: u32) -> f64 {
+        unit_float(sample.hash(&[sample.index, dimension as u64]))
+    }
+
+    fn name(&self) -> &'static str {
+        "independent"
+    }
+}
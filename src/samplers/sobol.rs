@@ -0,0 +1,55 @@
+use super::{PixelSample, Sampler};
+
+/// ### Sobol
+///
+/// Owen-scrambled Sobol points. Dimensions come in pairs that each form a 2D Sobol sequence, so
+/// every pair is stratified on its own; the pairs get their own shuffle of the sample order and
+/// their own scramble, which keeps them from correlating. Follows Burley's
+/// [Practical Hash-based Owen Scrambling](https://jcgt.org/published/0009/04/01/).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sobol;
+
+impl Sampler for Sobol {
+    fn get(&self, sample: &PixelSample, dimension: u32) -> f64 {
+        sobol_point(sample, dimension, sample.hash(&[dimension as u64 / 2]))
+    }
+
+    fn name(&self) -> &'static str {
+        "sobol"
+    }
+}
+
+/// Owen-scrambled Sobol value of `dimension`, with every random choice made from `key`
+pub(crate) fn sobol_point(sample: &PixelSample, dimension: u32, key: u64) -> f64 {
+    let index = nested_uniform_scramble(sample.index as u32, key as u32);
+    let value = match dimension % 2 {
+        0 => index.reverse_bits(),
+        _ => second_dimension(index),
+    };
+    let scrambled = nested_uniform_scramble(value, (key >> 32) as u32 ^ (dimension % 2));
+    scrambled as f64 / (1u64 << 32) as f64
+}
+
+/// Second dimension of the Sobol sequence, from the polynomial x + 1
+fn second_dimension(index: u32) -> u32 {
+    let mut direction = 1 << 31;
+    let mut value = 0;
+    for bit in 0..32 {
+        if index >> bit & 1 == 1 {
+            value ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+    value
+}
+
+/// Owen scramble of the bits of `value`, every choice made by hashing with `seed`
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    let mut value = value.reverse_bits();
+    value ^= value.wrapping_mul(0x3d20adea);
+    value = value.wrapping_add(seed);
+    value = value.wrapping_mul((seed >> 16) | 1);
+    value ^= value.wrapping_mul(0x05526c56);
+    value ^= value.wrapping_mul(0x53a22864);
+    value.reverse_bits()
+}
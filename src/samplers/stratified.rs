@@ -0,0 +1,64 @@
+use super::{PixelSample, Sampler};
+use crate::random::unit_float;
+
+/// ### Stratified
+///
+/// Splits every dimension into as many strata as the pixel has samples, and gives each sample a
+/// jittered point in its own stratum. The strata are shuffled separately for every dimension, so
+/// the samples of a pixel cover each dimension evenly. Samples past the pixel's count start a new
+/// round of strata.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stratified;
+
+impl Sampler for Stratified {
+    fn get(&self, sample: &PixelSample, dimension: u32) -> f64 {
+        let count = sample.count.clamp(1, u32::MAX as u64);
+        let (round, index) = (sample.index / count, sample.index % count);
+        let key = sample.hash(&[dimension as u64, round]);
+
+        let stratum = permute(index as u32, count as u32, key as u32);
+        let jitter = unit_float(sample.hash(&[key, index]));
+        (stratum as f64 + jitter) / count as f64
+    }
+
+    fn name(&self) -> &'static str {
+        "stratified"
+    }
+}
+
+/// Position of `index` in a random shuffle of `0..length` picked by `key`, from Kensler's
+/// [Correlated Multi-Jittered Sampling](https://graphics.pixar.com/library/MultiJitteredSampling/)
+pub(crate) fn permute(mut index: u32, length: u32, key: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    // Shuffle within the next power of two, and walk the cycle until landing inside `length`
+    loop {
+        index ^= key;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= key >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= key >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= key >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | key >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < length {
+            break;
+        }
+    }
+    index.wrapping_add(key) % length
+}
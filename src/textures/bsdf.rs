@@ -0,0 +1,126 @@
+use super::{BsdfSample, Texture};
+use crate::type_aliases::{Color, Direction, Directions, Normal};
+use nalgebra::Vector3;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Scattering behaviour of every texture, used by the path tracer.
+///
+/// All colors returned here are factors in the 0.0-1.0 range, while emission keeps the 0-255
+/// range of the texture colors.
+impl Texture {
+    /// Radiance given off by the surface, if any
+    pub fn emission(&self) -> Option<Color> {
+        match self {
+            Texture::Light(emission) => Some(emission.radiance()),
+            Texture::Principled(material) if material.is_emissive() => Some(material.emission),
+            _ => None,
+        }
+    }
+
+    /// Color of the surface without any lighting, for the albedo AOV
+    pub fn albedo(&self) -> Color {
+        match self {
+            Texture::Diffusive(color) => color / 255.0,
+            Texture::Principled(material) => material.base_color / 255.0,
+            Texture::Light(emission) => emission.color / 255.0,
+            Texture::Reflective => Color::repeat(1.0),
+        }
+    }
+
+    /// Whether light arriving from any direction can scatter off the surface, unlike a mirror
+    /// that only reflects a single direction. Only those surfaces benefit from sampling the
+    /// lights directly.
+    pub fn has_non_delta_lobe(&self) -> bool {
+        matches!(self, Texture::Diffusive(_) | Texture::Principled(_))
+    }
+
+    /// BSDF for light arriving from `wi` and leaving towards `wo`, both pointing away from the
+    /// surface. Mirrors and lights evaluate to black.
+    pub fn eval(&self, wo: Direction, wi: Direction, normal: Normal) -> Color {
+        match self {
+            Texture::Diffusive(color) => {
+                if same_side(wo, wi, normal) {
+                    color / (255.0 * PI)
+                } else {
+                    Color::zeros()
+                }
+            }
+            Texture::Principled(material) => material.eval(wo, wi, normal),
+            Texture::Reflective | Texture::Light(_) => Color::zeros(),
+        }
+    }
+
+    /// Probability density of `sample` picking `wi`
+    pub fn pdf(&self, wo: Direction, wi: Direction, normal: Normal) -> f64 {
+        match self {
+            Texture::Diffusive(_) => {
+                if same_side(wo, wi, normal) {
+                    wi.dot(&normal).abs() / PI
+                } else {
+                    0.0
+                }
+            }
+            Texture::Principled(material) => material.pdf(wo, wi, normal),
+            Texture::Reflective | Texture::Light(_) => 0.0,
+        }
+    }
+
+    /// Sample the direction a ray travelling along `incoming` continues in after hitting the
+    /// surface. Lights absorb every ray.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        incoming: Direction,
+        normal: Normal,
+        rng: &mut R,
+    ) -> Option<BsdfSample> {
+        match self {
+            Texture::Diffusive(color) => {
+                let facing_normal = if incoming.dot(&normal) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                let direction = facing_normal.to_world(&cosine_hemisphere(rng.gen(), rng.gen()));
+                Some(BsdfSample {
+                    direction,
+                    weight: color / 255.0,
+                    pdf: direction.dot(&facing_normal) / PI,
+                    delta: false,
+                })
+            }
+            Texture::Reflective => Some(BsdfSample {
+                direction: incoming - 2.0 * incoming.dot(&normal) * normal,
+                weight: Color::repeat(1.0),
+                pdf: 1.0,
+                delta: true,
+            }),
+            Texture::Principled(material) => material.sample(incoming, normal, rng),
+            Texture::Light(_) => None,
+        }
+    }
+}
+
+fn same_side(wo: Direction, wi: Direction, normal: Normal) -> bool {
+    wo.dot(&normal) * wi.dot(&normal) > 0.0
+}
+
+/// Cosine weighted direction around the `z` axis
+pub fn cosine_hemisphere(u: f64, v: f64) -> Vector3<f64> {
+    let radius = u.sqrt();
+    let phi = 2.0 * PI * v;
+    Vector3::new(
+        radius * phi.cos(),
+        radius * phi.sin(),
+        (1.0 - u).max(0.0).sqrt(),
+    )
+}
+
+/// Weight of a sample taken with `pdf`, when `other_pdf` could also have produced it
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b <= 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
@@ -0,0 +1,68 @@
+use crate::config::textures::REFERENCE_WHITE_NITS;
+use crate::type_aliases::Color;
+
+/// ### Emission
+///
+/// Light given off by a surface. `color` uses the 0-255 range of the rest of the crate and only
+/// sets the tint, while `strength` is the luminance in nits of the light when its color is white.
+///
+/// At an exposure of 0, a white light of `REFERENCE_WHITE_NITS` (100 nits, about a computer
+/// screen) shows as white, and every stop of exposure doubles what the image shows. Brighter
+/// lights, such as a 10000 nits lamp, need a lower exposure to stay in range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emission {
+    pub color: Color,
+    pub strength: f64,
+}
+
+impl Emission {
+    pub fn new(color: Color, strength: f64) -> Self {
+        Self {
+            color,
+            strength: strength.max(0.0),
+        }
+    }
+
+    /// Emitter with the color of a black body at `kelvin` degrees.
+    ///
+    /// Candle light is around 1900K, incandescent bulbs 2700K, daylight 6500K and clear sky 10000K.
+    pub fn from_kelvin(kelvin: f64, strength: f64) -> Self {
+        Self::new(blackbody(kelvin), strength)
+    }
+
+    /// Emitted radiance on the 0-255 scale of the image, where 255 is `REFERENCE_WHITE_NITS`
+    pub fn radiance(&self) -> Color {
+        self.color * (self.strength / REFERENCE_WHITE_NITS)
+    }
+}
+
+/// Approximation of the sRGB color of a black body, valid from 1000K to 40000K
+pub fn blackbody(kelvin: f64) -> Color {
+    let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (temperature - 60.0).powf(-0.1332047592)
+    };
+
+    let green = if temperature <= 66.0 {
+        99.4708025861 * temperature.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (temperature - 60.0).powf(-0.0755148492)
+    };
+
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (temperature - 10.0).ln() - 305.0447927307
+    };
+
+    Color::new(
+        red.clamp(0.0, 255.0),
+        green.clamp(0.0, 255.0),
+        blue.clamp(0.0, 255.0),
+    )
+}
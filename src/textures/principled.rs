@@ -0,0 +1,321 @@
+use super::cosine_hemisphere;
+use crate::color::RGB;
+use crate::type_aliases::{Color, Direction, Directions, Normal};
+use nalgebra::Vector3;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Roughness of the clearcoat layer. Disney exposes this as `clearcoatGloss`, we keep it fixed.
+const CLEARCOAT_ALPHA: f64 = 0.05;
+
+/// ### Principled
+///
+/// Artist-friendly material modelled after the Disney principled BSDF.
+///
+/// `base_color` and `emission` use the same 0-255 range as the rest of the colors in the crate,
+/// every other parameter is a factor between 0.0 and 1.0 (except `ior`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Principled {
+    pub base_color: Color,
+    pub metallic: f64,
+    pub roughness: f64,
+    pub specular: f64,
+    pub clearcoat: f64,
+    pub sheen: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    pub emission: Color,
+}
+
+/// Direction picked by `Principled::sample`.
+///
+/// `weight` is `bsdf * cos / pdf`, the factor the path throughput should be multiplied with.
+/// `delta` is set for the transmission lobe, which can not be evaluated with `Principled::eval`.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    pub direction: Direction,
+    pub weight: Color,
+    pub pdf: f64,
+    pub delta: bool,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self::new(Color::grey())
+    }
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Self {
+        Self {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            clearcoat: 0.0,
+            sheen: 0.0,
+            transmission: 0.0,
+            ior: 1.5,
+            emission: Color::black(),
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission.max() > 0.0
+    }
+
+    /// Evaluates the reflective lobes for light arriving from `wi` and leaving towards `wo`.
+    ///
+    /// Both directions point away from the surface. The transmission lobe is not included.
+    pub fn eval(&self, wo: Direction, wi: Direction, normal: Normal) -> Color {
+        let normal = facing(normal, wo);
+        let cos_o = normal.dot(&wo);
+        let cos_i = normal.dot(&wi);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::black();
+        }
+
+        let half = (wo + wi).normalize();
+        let cos_h = normal.dot(&half);
+        let cos_d = wi.dot(&half);
+        let base = self.albedo();
+
+        // Diffuse with retro-reflection, and sheen at grazing angles
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let diffuse = base / PI
+            * (1.0 + (fd90 - 1.0) * schlick_weight(cos_i))
+            * (1.0 + (fd90 - 1.0) * schlick_weight(cos_o));
+        let sheen = Color::repeat(self.sheen * schlick_weight(cos_d));
+        let dielectric = (1.0 - self.metallic) * (1.0 - self.transmission);
+
+        // Specular microfacet lobe
+        let alpha = self.alpha();
+        let f0 = self.specular_tint();
+        let fresnel = f0 + (Color::repeat(1.0) - f0) * schlick_weight(cos_d);
+        let specular =
+            fresnel * ggx_d(cos_h, alpha) * smith_g1(cos_i, alpha) * smith_g1(cos_o, alpha)
+                / (4.0 * cos_i * cos_o);
+
+        // Clearcoat layer
+        let clearcoat_fresnel = 0.04 + 0.96 * schlick_weight(cos_d);
+        let clearcoat = 0.25
+            * self.clearcoat
+            * gtr1_d(cos_h, CLEARCOAT_ALPHA)
+            * clearcoat_fresnel
+            * smith_g1(cos_i, 0.25)
+            * smith_g1(cos_o, 0.25)
+            / (4.0 * cos_i * cos_o);
+
+        // The transmission lobe already accounts for the reflections off glass
+        let specular = specular * (1.0 - (1.0 - self.metallic) * self.transmission);
+
+        (diffuse + sheen) * dielectric + specular + Color::repeat(clearcoat)
+    }
+
+    /// Probability density of `sample` picking `wi`, ignoring the transmission lobe.
+    pub fn pdf(&self, wo: Direction, wi: Direction, normal: Normal) -> f64 {
+        let normal = facing(normal, wo);
+        let cos_i = normal.dot(&wi);
+        if normal.dot(&wo) <= 0.0 || cos_i <= 0.0 {
+            return 0.0;
+        }
+
+        let [diffuse, specular, clearcoat, _] = self.lobe_probabilities();
+        let half = (wo + wi).normalize();
+        let cos_h = normal.dot(&half);
+        let jacobian = 1.0 / (4.0 * wo.dot(&half).abs().max(1e-8));
+
+        diffuse * cos_i / PI
+            + specular * ggx_d(cos_h, self.alpha()) * cos_h * jacobian
+            + clearcoat * gtr1_d(cos_h, CLEARCOAT_ALPHA) * cos_h * jacobian
+    }
+
+    /// Importance samples an incoming direction for a ray travelling along `incoming`.
+    ///
+    /// Returns `None` when the sampled direction ends up below the surface.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        incoming: Direction,
+        normal: Normal,
+        rng: &mut R,
+    ) -> Option<BsdfSample> {
+        let wo = -incoming.normalize();
+        let [diffuse, specular, _, transmission] = self.lobe_probabilities();
+        let pick: f64 = rng.gen();
+
+        if pick >= 1.0 - transmission {
+            return self.sample_transmission(wo, normal, rng);
+        }
+
+        let facing_normal = facing(normal, wo);
+        let (u, v): (f64, f64) = (rng.gen(), rng.gen());
+        let direction = if pick < diffuse {
+            facing_normal.to_world(&cosine_hemisphere(u, v))
+        } else {
+            let local_half = if pick < diffuse + specular {
+                ggx_half_vector(u, v, self.alpha())
+            } else {
+                gtr1_half_vector(u, v, CLEARCOAT_ALPHA)
+            };
+            let half = facing_normal.to_world(&local_half);
+            2.0 * wo.dot(&half) * half - wo
+        };
+
+        let pdf = self.pdf(wo, direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        let cos_i = facing_normal.dot(&direction);
+        Some(BsdfSample {
+            direction,
+            weight: self.eval(wo, direction, normal) * cos_i / pdf,
+            pdf,
+            delta: false,
+        })
+    }
+
+    /// Rough dielectric lobe. Reflects or refracts around a GGX microfacet normal
+    /// based on the fresnel term, tinted by the base color.
+    fn sample_transmission<R: Rng + ?Sized>(
+        &self,
+        wo: Direction,
+        normal: Normal,
+        rng: &mut R,
+    ) -> Option<BsdfSample> {
+        let entering = normal.dot(&wo) > 0.0;
+        let facing_normal = facing(normal, wo);
+        let eta = if entering { 1.0 / self.ior } else { self.ior };
+
+        let half = facing_normal.to_world(&ggx_half_vector(rng.gen(), rng.gen(), self.alpha()));
+        let cos_o = wo.dot(&half).max(0.0);
+        let reflected = 2.0 * cos_o * half - wo;
+
+        let (direction, refracted) = match refract(wo, half, eta) {
+            Some(refracted) if rng.gen::<f64>() >= dielectric_fresnel(cos_o, eta) => {
+                (refracted, true)
+            }
+            _ => (reflected, false),
+        };
+
+        // Rough microfacets can send the ray to the wrong side of the surface
+        if (direction.dot(&facing_normal) < 0.0) != refracted {
+            return None;
+        }
+
+        let tint = if refracted {
+            self.albedo()
+        } else {
+            Color::repeat(1.0)
+        };
+
+        Some(BsdfSample {
+            direction,
+            weight: tint,
+            pdf: 1.0,
+            delta: true,
+        })
+    }
+
+    /// Base color scaled down to the 0.0-1.0 range
+    fn albedo(&self) -> Color {
+        self.base_color / 255.0
+    }
+
+    fn alpha(&self) -> f64 {
+        (self.roughness * self.roughness).max(1e-3)
+    }
+
+    /// Reflectance at normal incidence, metals tint their reflections with the base color
+    pub(crate) fn specular_tint(&self) -> Color {
+        let dielectric = Color::repeat(0.08 * self.specular);
+        dielectric.lerp(&self.albedo(), self.metallic)
+    }
+
+    /// Chance of `sample` picking the diffuse, specular, clearcoat and transmission lobes
+    fn lobe_probabilities(&self) -> [f64; 4] {
+        let luminance = |c: Color| 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+        let diffuse = (1.0 - self.metallic)
+            * (1.0 - self.transmission)
+            * (luminance(self.albedo()) + self.sheen).max(1e-3);
+        let specular = luminance(self.specular_tint()).max(0.02)
+            * (1.0 - (1.0 - self.metallic) * self.transmission);
+        let clearcoat = 0.25 * self.clearcoat;
+        let transmission = (1.0 - self.metallic) * self.transmission;
+
+        let total = diffuse + specular + clearcoat + transmission;
+        [
+            diffuse / total,
+            specular / total,
+            clearcoat / total,
+            transmission / total,
+        ]
+    }
+}
+
+/// Flip the normal to the side of the surface `direction` is on
+fn facing(normal: Normal, direction: Direction) -> Normal {
+    if normal.dot(&direction) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+fn ggx_d(cos_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let t = cos_h * cos_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * t * t)
+}
+
+fn gtr1_d(cos_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+fn smith_g1(cos: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
+
+pub(crate) fn dielectric_fresnel(cos_i: f64, eta: f64) -> f64 {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+/// Refracts `wo` through a surface with normal `normal`. `None` on total internal reflection.
+pub(crate) fn refract(wo: Direction, normal: Normal, eta: f64) -> Option<Direction> {
+    let cos_i = wo.dot(&normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((-wo * eta + normal * (eta * cos_i - cos_t)).normalize())
+}
+
+fn ggx_half_vector(u: f64, v: f64, alpha: f64) -> Vector3<f64> {
+    let cos_theta = ((1.0 - u) / (1.0 + (alpha * alpha - 1.0) * u)).sqrt();
+    spherical(cos_theta, 2.0 * PI * v)
+}
+
+fn gtr1_half_vector(u: f64, v: f64, alpha: f64) -> Vector3<f64> {
+    let a2 = alpha * alpha;
+    let cos_theta = ((1.0 - a2.powf(1.0 - u)) / (1.0 - a2)).max(0.0).sqrt();
+    spherical(cos_theta, 2.0 * PI * v)
+}
+
+fn spherical(cos_theta: f64, phi: f64) -> Vector3<f64> {
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    Vector3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}